@@ -0,0 +1,77 @@
+//! Embeds build information that the stats command reports.
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process::Command;
+
+fn main() {
+    let commit = output("git", &["rev-parse", "--short", "HEAD"]);
+    let rustc = output(&env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned()), &["--version"]);
+    let profile = env::var("PROFILE").unwrap_or_else(|_| "unknown".to_owned());
+
+    println!("cargo:rustc-env=NANOBOT_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=NANOBOT_BUILD_PROFILE={}", profile);
+    println!("cargo:rustc-env=NANOBOT_RUSTC_VERSION={}", rustc);
+    println!("cargo:rustc-env=NANOBOT_SERENITY_VERSION={}", serenity_version());
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/packed-refs");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    // HEAD only changes when switching branches; committing moves the ref it
+    // points to instead.
+    if let Some(head_ref) = head_ref() {
+        println!("cargo:rerun-if-changed=.git/{}", head_ref);
+    }
+}
+
+/// Reads the ref that `.git/HEAD` points to, such as `refs/heads/master`, if
+/// it isn't detached.
+fn head_ref() -> Option<String> {
+    let mut head = String::new();
+    File::open(".git/HEAD").and_then(|mut f| f.read_to_string(&mut head)).ok()?;
+
+    if head.starts_with("ref: ") {
+        Some(head["ref: ".len()..].trim().to_owned())
+    } else {
+        None
+    }
+}
+
+/// Runs a command, returning its trimmed stdout or "unknown" if it failed.
+fn output(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .and_then(|output| if output.status.success() {
+            String::from_utf8(output.stdout).ok()
+        } else {
+            None
+        })
+        .map_or_else(|| "unknown".to_owned(), |s| s.trim().to_owned())
+}
+
+/// Finds the version of serenity that was resolved in the lockfile.
+fn serenity_version() -> String {
+    let mut lock = String::new();
+
+    if File::open("Cargo.lock").and_then(|mut f| f.read_to_string(&mut lock)).is_err() {
+        return "unknown".to_owned();
+    }
+
+    let mut lines = lock.lines();
+
+    while let Some(line) = lines.next() {
+        if line.trim() == "name = \"serenity\"" {
+            if let Some(version) = lines.next() {
+                return version.trim()
+                    .trim_left_matches("version = \"")
+                    .trim_right_matches('"')
+                    .to_owned();
+            }
+        }
+    }
+
+    "unknown".to_owned()
+}
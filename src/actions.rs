@@ -0,0 +1,220 @@
+//! Outgoing side effects that honour dry-run mode.
+//!
+//! Replies to the channel a command was invoked in go out as usual, so that
+//! read-only commands keep working. Everything else that mutates state on
+//! Discord's end - automated posts, role edits, profile and presence changes -
+//! should go through these functions instead of calling serenity directly.
+//!
+//! When dry-run is on, globally or for the guild in question, the action is
+//! logged with its full payload and posted to the channel in the
+//! `DRY_RUN_CHANNEL_ID` env var instead of being performed.
+
+use serenity::builder::EditProfile;
+use serenity::client::bridge::gateway::ShardMessenger;
+use serenity::client::{Context, rest};
+use serenity::model::gateway::Game;
+use serenity::model::guild::Member;
+use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
+use serenity::model::user::OnlineStatus;
+use serenity::prelude::Mutex;
+use serenity::Result;
+use std::collections::HashSet;
+use std::env;
+use typemap::ShareMap;
+use ::misc::persist;
+use ::store::{DryRunConfig, NanoCache};
+
+const FILE_NAME: &'static str = "dry_run.json";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DryRun {
+    /// Whether dry-run is enabled for every guild and for global actions.
+    pub global: bool,
+    /// IDs of the guilds that dry-run is enabled for.
+    pub guilds: HashSet<u64>,
+}
+
+impl DryRun {
+    /// Loads the persisted config, enabling global dry-run if the `DRY_RUN`
+    /// env var is set to a truthy value.
+    pub fn load() -> Self {
+        let mut config: DryRun = persist::load(FILE_NAME);
+
+        if let Ok(value) = env::var("DRY_RUN") {
+            config.global |= value == "1" || value == "true";
+        }
+
+        config
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+
+    /// Whether actions for the given guild, or global actions if `None`,
+    /// should only be logged.
+    pub fn enabled(&self, guild_id: Option<GuildId>) -> bool {
+        self.global || guild_id.map_or(false, |id| self.guilds.contains(&id.0))
+    }
+}
+
+pub fn say(ctx: &Context, guild_id: Option<GuildId>, channel_id: ChannelId, content: &str)
+    -> Result<()> {
+    if is_dry(&ctx.data, guild_id) {
+        report(&format!("say in {}: {:?}", channel_id, content));
+
+        return Ok(());
+    }
+
+    channel_id.say(content).map(|_| ())
+}
+
+/// Whether a notification to the owner is sent in dry-run mode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DryRunPolicy {
+    /// Sent regardless, such as for errors and health warnings that the
+    /// owner needs to see while testing.
+    Send,
+    /// Only logged while dry-run mode is on globally, like other actions.
+    Report,
+}
+
+/// Posts to the channel in the given env var, falling back to DMing the bot
+/// owner.
+///
+/// Whether it's sent in dry-run mode is up to the given policy.
+pub fn notify_owner(data: &Mutex<ShareMap>, channel_var: &str, content: &str, policy: DryRunPolicy) {
+    if policy == DryRunPolicy::Report && is_dry(data, None) {
+        report(&format!("notify owner via {}: {:?}", channel_var, content));
+
+        return;
+    }
+
+    let channel_id = match env::var(channel_var).map(|x| x.parse::<u64>()) {
+        Ok(Ok(channel_id)) => ChannelId(channel_id),
+        _ => {
+            let owner_id = data.lock().get::<NanoCache>().unwrap().owner_id;
+
+            match owner_id.create_dm_channel() {
+                Ok(channel) => channel.id,
+                Err(why) => {
+                    warn!("Err opening DM with owner: {:?}", why);
+
+                    return;
+                },
+            }
+        },
+    };
+
+    if let Err(why) = channel_id.say(content) {
+        warn!("Err notifying owner: {:?}", why);
+    }
+}
+
+pub fn add_role(ctx: &Context, member: &mut Member, role_id: RoleId) -> Result<()> {
+    add_roles(ctx, member, &[role_id])
+}
+
+pub fn add_roles(ctx: &Context, member: &mut Member, role_ids: &[RoleId]) -> Result<()> {
+    if is_dry(&ctx.data, Some(member.guild_id)) {
+        let user_id = member.user.read().id;
+        report(&format!("add_roles to {} in {}: {:?}", user_id, member.guild_id, role_ids));
+
+        return Ok(());
+    }
+
+    member.add_roles(role_ids)
+}
+
+/// Removes a role from a member without needing the member itself, for use
+/// outside of event handlers and commands.
+pub fn remove_role(data: &Mutex<ShareMap>, guild_id: GuildId, user_id: UserId, role_id: RoleId)
+    -> Result<()> {
+    if is_dry(data, Some(guild_id)) {
+        report(&format!("remove role {} from {} in {}", role_id, user_id, guild_id));
+
+        return Ok(());
+    }
+
+    rest::remove_member_role(guild_id.0, user_id.0, role_id.0)
+}
+
+pub fn set_nickname(ctx: &Context, guild_id: GuildId, user_id: UserId, nick: &str)
+    -> Result<()> {
+    if is_dry(&ctx.data, Some(guild_id)) {
+        report(&format!("set nickname of {} in {}: {:?}", user_id, guild_id, nick));
+
+        return Ok(());
+    }
+
+    guild_id.edit_member(user_id, |m| m.nickname(nick))
+}
+
+pub fn leave_guild(ctx: &Context, guild_id: GuildId) -> Result<()> {
+    if is_dry(&ctx.data, Some(guild_id)) {
+        report(&format!("leave guild {}", guild_id));
+
+        return Ok(());
+    }
+
+    guild_id.leave().map(|_| ())
+}
+
+pub fn edit_profile<F>(ctx: &Context, f: F) -> Result<()>
+    where F: FnOnce(EditProfile) -> EditProfile {
+    if is_dry(&ctx.data, None) {
+        let payload = f(EditProfile::default());
+        report(&format!("edit_profile: {:?}", payload.0));
+
+        return Ok(());
+    }
+
+    ctx.edit_profile(f).map(|_| ())
+}
+
+pub fn set_presence(data: &Mutex<ShareMap>,
+                    shard_id: u64,
+                    messenger: &ShardMessenger,
+                    game: Option<Game>,
+                    status: OnlineStatus) {
+    if is_dry(data, None) {
+        report(&format!("set_presence on shard {}: {:?} {:?}", shard_id, game, status));
+
+        return;
+    }
+
+    messenger.set_presence(game, status);
+}
+
+/// Whether dry-run mode is on, globally or for the guild.
+pub fn is_dry(data: &Mutex<ShareMap>, guild_id: Option<GuildId>) -> bool {
+    let data = data.lock();
+
+    data.get::<DryRunConfig>().map_or(false, |config| config.enabled(guild_id))
+}
+
+fn report(description: &str) {
+    info!("[dry run] {}", description);
+
+    let channel_id = match env::var("DRY_RUN_CHANNEL_ID").map(|x| x.parse::<u64>()) {
+        Ok(Ok(channel_id)) => ChannelId(channel_id),
+        _ => return,
+    };
+
+    let mut content = format!("**[dry run]** {}", description);
+
+    if content.len() > 2000 {
+        let mut end = 1997;
+
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        content.truncate(end);
+        content.push_str("...");
+    }
+
+    if let Err(why) = channel_id.say(&content) {
+        warn!("Err posting dry run report: {:?}", why);
+    }
+}
@@ -27,17 +27,13 @@ impl Command for ModPingCommand {
 
         let chosen_mod = match found_mod {
             Some(chosen_mod) => chosen_mod,
-            None => {
-                let _ = msg.channel_id.say("There are no online mods to ping.");
-
-                return Ok(());
-            },
+            None => return Err(Error::NotFound("any online mods to ping".to_owned()).into()),
         };
 
         let content = format!("{}, you were pinged for a mod action by **{}**.",
                             chosen_mod.mention(),
                             msg.author.tag());
-        let _ = msg.channel_id.say(&content);
+        msg.channel_id.say(&content).map_err(Error::from)?;
 
         Ok(())
     }
@@ -49,15 +45,10 @@ pub struct UdefineCommand;
 impl Command for UdefineCommand {
     fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
         if args.is_empty() {
-            let _ = msg.channel_id.say("No word given");
-
-            return Ok(());
+            return Err(Error::BadArgument("No word given".to_owned()).into());
         }
 
-        let mut msg = match msg.channel_id.say("Searching for definition...") {
-            Ok(msg) => msg,
-            Err(_) => return Ok(()),
-        };
+        let mut msg = msg.channel_id.say("Searching for definition...").map_err(Error::from)?;
 
         let query = args.full();
 
@@ -66,20 +57,18 @@ impl Command for UdefineCommand {
         let mut response = match client.definitions(&query[..]) {
             Ok(response) => response,
             Err(why) => {
-                warn!("Err retrieving word '{}': {:?}", query, why);
-
-                let _ = msg.channel_id.say("Error retrieving definition");
+                let _ = msg.delete();
 
-                return Ok(());
+                return Err(Error::upstream("Urban Dictionary", why).into());
             },
         };
 
         let definition = match response.definitions.get_mut(0) {
             Some(definition) => definition,
             None => {
-                let _ = msg.edit(|m| m.content("No definition found"));
+                let _ = msg.delete();
 
-                return Ok(());
+                return Err(Error::NotFound(format!("a definition for '{}'", query)).into());
             },
         };
 
@@ -91,7 +80,7 @@ impl Command for UdefineCommand {
         let url = format!("https://www.urbandictionary.com/author.php?author={}",
                         definition.author);
 
-        let _ = msg.edit(|m| m
+        msg.edit(|m| m
             .embed(|e| e
                 .title(&format!("Definition for **{}**", definition.word))
                 .description(&definition.definition)
@@ -101,7 +90,7 @@ impl Command for UdefineCommand {
                     .url(&url.replace(' ', "%20")))
                 .field("Permalink", &format!("[#{}]({})", definition.id, definition.permalink), true)
                 .field(":+1:", &definition.thumbs_up.to_string(), true)
-                .field(":-1:", &definition.thumbs_down.to_string(), true)));
+                .field(":-1:", &definition.thumbs_down.to_string(), true))).map_err(Error::from)?;
 
         Ok(())
     }
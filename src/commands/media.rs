@@ -9,35 +9,28 @@ pub struct AnimeCommand;
 impl Command for AnimeCommand {
     fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
         if args.is_empty() {
-            let _ = msg.channel_id.say("A name must be given");
-
-            return Ok(());
+            return Err(Error::BadArgument("A name must be given".to_owned()).into());
         }
 
         let query = args.full();
 
-        let mut msg = match msg.channel_id.say(&format!("Searching for '{}'...", query)) {
-            Ok(msg) => msg,
-            Err(_) => return Ok(()),
-        };
+        let mut msg = msg.channel_id.say(&format!("Searching for '{}'...", query)).map_err(Error::from)?;
 
         let client = Client::new();
 
         let mut series_list = match client.search_anime(|f| f.filter("text", &query[..])) {
             Ok(series_list) => series_list.data,
             Err(why) => {
-                warn!("Err getting anime series '{}': {:?}", query, why);
-
-                let _ = msg.edit(|m| m.content("Error retrieving listing"));
+                let _ = msg.delete();
 
-                return Ok(());
+                return Err(Error::upstream("Kitsu", why).into());
             },
         };
 
         if series_list.is_empty() {
-            let _ = msg.edit(|m| m.content("No results found"));
+            let _ = msg.delete();
 
-            return Ok(());
+            return Err(Error::NotFound(format!("any anime named '{}'", query)).into());
         }
 
         let series = {
@@ -79,7 +72,7 @@ impl Command for AnimeCommand {
             AnimeType::TV => "TV",
         };
 
-        let _ = msg.edit(|m| m
+        msg.edit(|m| m
             .embed(move |mut e| {
                 e = e.title(&title)
                     .description(&description)
@@ -94,7 +87,7 @@ impl Command for AnimeCommand {
                 }
 
                 e
-            }));
+            })).map_err(Error::from)?;
 
         Ok(())
     }
@@ -1,71 +1,201 @@
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use reqwest::header::Authorization;
+use reqwest::Client;
+use serde_json::Value;
+use serenity::client::rest::ratelimiting::{Route, GLOBAL, ROUTES};
+use serenity::client::rest::{self, LightMethod};
 use serenity::client::CACHE;
+use serenity::framework::standard::{help_commands, CommandGroup};
+use serenity::model::channel::{ChannelType, PermissionOverwriteType};
+use serenity::model::gateway::GameType;
+use serenity::model::guild::{Guild, Role, VerificationLevel};
+use serenity::model::id::{ChannelId, EmojiId, UserId};
+use serenity::model::user::OnlineStatus;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Instant;
 use std::u64;
-use ::store::ShardUptime;
+use unicode_names2;
+use ::commands;
+use ::misc::{self, duration, format_latency, Snowflake};
+use ::misc::permissions::{self, Explanation};
 use ::prelude::*;
+use ::resolver::{self, Resolved};
+use ::store::{BootHistoryStore, CommandToggleStore, EmojiUsageStore, ShardHealth, ShardManagerContainer, ShardUptime};
 
-macro_rules! permissions {
-    ($perms:ident; $($f:ident $n:expr,)*) => {
-        {
-            let mut s = vec![];
-
-            $(
-                if $perms.$f() {
-                    s.push($n);
-                }
-            )*
-
-            s
-        }
-    }
-}
+/// The permissions userinfo highlights: kick, ban, administrator, manage
+/// channels, guild, messages, roles and webhooks, and mention everyone.
+const KEY_PERMISSIONS: u64 = 1 << 1 | 1 << 2 | 1 << 3 | 1 << 4 | 1 << 5 | 1 << 13 | 1 << 17 | 1 << 28 | 1 << 29;
 
 pub struct AvatarCommand;
 
 impl Command for AvatarCommand {
     fn execute(&self, _: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
-        let url = if let Some(user) = msg.mentions.first() {
-            user.avatar_url()
-        } else if let Ok(arg) = args.single::<String>() {
-            let guild_id = CACHE.read()
-                .guild_channel(msg.channel_id)
-                .map(|c| c.read().guild_id);
-
-            let guild_id = match guild_id {
-                Some(guild_id) => guild_id,
-                None => {
-                    let _ = msg.channel_id.say("Could not find server data.");
+        const FORMATS: [&'static str; 4] = ["png", "jpg", "webp", "gif"];
 
-                    return Ok(());
-                },
-            };
+        let mut size = None;
+        let mut format = None;
+        let mut words = vec![];
 
-            let avatar_url = CACHE.read()
-                .guild(guild_id)
-                .map(|g| g.read().member_named(&arg).map(|m| m.user.read().avatar_url()));
+        while let Ok(word) = args.single::<String>() {
+            let lower = word.to_lowercase();
 
-            match avatar_url {
-                Some(Some(avatar_url)) => avatar_url,
-                Some(None) => {
-                    let _ = msg.channel_id.say("Could not find avatar");
+            match lower.parse::<u64>() {
+                // IDs are far larger than any size, so small numbers are sizes.
+                Ok(n) if n <= 4096 => {
+                    if n < 16 || !n.is_power_of_two() {
+                        return Err(Error::BadArgument("Size must be a power of two from 16 to 4096".to_owned()).into());
+                    }
 
-                    return Ok(());
+                    size = Some(n);
                 },
-                None => {
-                    let _ = msg.channel_id.say("Could not find user");
-
-                    return Ok(());
+                _ if FORMATS.iter().any(|f| *f == lower) || lower == "jpeg" => {
+                    format = Some(if lower == "jpeg" { "jpg".to_owned() } else { lower });
                 },
+                _ => words.push(word),
             }
+        }
+
+        let user = if words.is_empty() {
+            msg.author.clone()
         } else {
-            msg.author.avatar_url()
+            resolver::user(msg.guild_id(), &words.join(" ")).or_error("user", resolver::describe_user)?
         };
 
-        let _ = if let Some(url) = url {
-            msg.channel_id.say(&url)
+        let size = size.unwrap_or(1024);
+
+        let (image, links) = match user.avatar {
+            Some(ref hash) => {
+                let animated = hash.starts_with("a_");
+                let url = |ext: &str| format!("https://cdn.discordapp.com/avatars/{}/{}.{}?size={}",
+                                              user.id,
+                                              hash,
+                                              ext,
+                                              size);
+
+                if format.as_ref().map_or(false, |f| f == "gif") && !animated {
+                    return Err(Error::BadArgument("That avatar isn't animated".to_owned()).into());
+                }
+
+                let default_format = if animated { "gif" } else { "png" };
+                let image = url(format.as_ref().map_or(default_format, |f| &f[..]));
+                let links = FORMATS.iter()
+                    .filter(|ext| animated || **ext != "gif")
+                    .map(|ext| format!("[{}]({})", ext, url(*ext)))
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+
+                (image, links)
+            },
+            // Default avatars only come as PNGs of a fixed size.
+            None => {
+                let url = user.default_avatar_url();
+
+                (url.clone(), format!("[png]({}) (default avatar)", url))
+            },
+        };
+
+        msg.channel_id.send_message(|m| m
+            .embed(|e| e
+                .title(&format!("Avatar for {}", user.tag()))
+                .description(&links)
+                .image(&image))).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct ChannelInfoCommand;
+
+impl Command for ChannelInfoCommand {
+    fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
+        };
+        let resolved = if args.is_empty() {
+            guild.read()
+                .channels
+                .get(&msg.channel_id)
+                .map_or(Resolved::NotFound, |channel| Resolved::Found(channel.read().clone()))
         } else {
-            msg.channel_id.say("Could not find avatar")
+            resolver::channel(&guild.read(), msg.author.id, args.full())
+        };
+
+        let channel = resolved.or_error("channel", resolver::describe_channel)?;
+
+        let kind = match channel.kind {
+            ChannelType::Category => "Category",
+            ChannelType::Group => "Group",
+            ChannelType::Private => "Private",
+            ChannelType::Text => "Text",
+            ChannelType::Voice => "Voice",
+        };
+        let created = format!("{} UTC", channel.id.created_at().format("%Y-%m-%d %H:%M:%S"));
+        let nsfw = if channel.nsfw { "Yes" } else { "No" };
+        let slowmode = match slowmode(channel.id) {
+            Some(0) => "Off".to_owned(),
+            Some(seconds) => format!("{}s", seconds),
+            None => "Unknown".to_owned(),
         };
+        let topic = channel.topic.clone()
+            .and_then(|topic| if topic.is_empty() { None } else { Some(topic) })
+            .unwrap_or_else(|| "None".to_owned());
+
+        let mut overwrites = String::new();
+
+        let category = {
+            let guild = guild.read();
+
+            for overwrite in &channel.permission_overwrites {
+                let target = match overwrite.kind {
+                    PermissionOverwriteType::Member(user_id) => match guild.members.get(&user_id) {
+                        Some(member) => member.user.read().tag(),
+                        None => user_id.to_string(),
+                    },
+                    PermissionOverwriteType::Role(role_id) => match guild.roles.get(&role_id) {
+                        Some(role) => role.name.clone(),
+                        None => role_id.to_string(),
+                    },
+                };
+
+                let _ = write!(overwrites, "**{}**:", target);
+
+                if !overwrite.allow.is_empty() {
+                    let _ = write!(overwrites, " allow {}", permissions::names(overwrite.allow.bits()).join(", "));
+                }
+
+                if !overwrite.deny.is_empty() {
+                    let _ = write!(overwrites, " deny {}", permissions::names(overwrite.deny.bits()).join(", "));
+                }
+
+                overwrites.push('\n');
+            }
+
+            channel.category_id
+                .and_then(|id| guild.channels.get(&id))
+                .map_or_else(|| "None".to_owned(), |c| c.read().name.clone())
+        };
+
+        if overwrites.is_empty() {
+            overwrites.push_str("None");
+        } else {
+            truncate_lines(&mut overwrites, 1024);
+        }
+
+        msg.channel_id.send_message(|m| m
+            .embed(|e| e
+                .title(&format!("Channel info for #{} ({})", channel.name, channel.id))
+                .description(&topic)
+                .field("Type", kind, true)
+                .field("Category", &category, true)
+                .field("Position", &channel.position.to_string(), true)
+                .field("NSFW", nsfw, true)
+                .field("Slowmode", &slowmode, true)
+                .field("Created", &created, true)
+                .field("Permission Overwrites", &overwrites, false))).map_err(Error::from)?;
 
         Ok(())
     }
@@ -74,20 +204,246 @@ impl Command for AvatarCommand {
 pub struct RpingCommand;
 
 impl Command for RpingCommand {
-    fn execute(&self, _: &mut Context, msg: &Message, _: Args) -> CommandResult {
-        let start = Utc::now();
-        let mut msg = req!(msg.channel_id.say("Ping!"));
+    fn execute(&self, _: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        const MAX_ROUNDS: usize = 10;
+
+        let rounds = match args.single::<usize>() {
+            Ok(rounds) if rounds >= 1 && rounds <= MAX_ROUNDS => rounds,
+            Ok(_) => {
+                return Err(Error::BadArgument(format!("Must be between 1 and {}", MAX_ROUNDS)).into());
+            },
+            Err(_) => 3,
+        };
+
+        let start = Instant::now();
+        let mut reply = req!(msg.channel_id.say("Ping!"));
+        let send = vec![elapsed_ms(start)];
+
+        let mut edit = vec![];
+        let mut get = vec![];
+
+        for round in 1..rounds + 1 {
+            let start = Instant::now();
+
+            if reply.edit(|m| m.content(&format!("Ping! `[{}/{}]`", round, rounds))).is_ok() {
+                edit.push(elapsed_ms(start));
+            }
+
+            let start = Instant::now();
+
+            if rest::get_current_user().is_ok() {
+                get.push(elapsed_ms(start));
+            }
+        }
+
+        let mut all = send.iter().chain(&edit).chain(&get).cloned().collect::<Vec<_>>();
+
+        let mut s = "Pong!\n```\nKind | Samples | Min    | Median | P95    | Max\n".to_owned();
+
+        let mut rows = [("Send", send), ("Edit", edit), ("Get", get)];
+
+        for row in rows.iter_mut() {
+            s.push_str(&latency_row(row.0, &mut row.1));
+        }
+
+        s.push_str(&latency_row("All", &mut all));
+        s.push_str("```\n");
+
+        let now = Utc::now().timestamp();
+        let buckets = [
+            ("Send", Route::ChannelsIdMessages(msg.channel_id.0)),
+            ("Edit", Route::ChannelsIdMessagesId(LightMethod::Patch, msg.channel_id.0)),
+            ("Get", Route::UsersMe),
+        ];
+        let mut limited = false;
+
+        {
+            let routes = ROUTES.lock();
+
+            for &(name, ref route) in buckets.iter() {
+                let _ = match routes.get(route) {
+                    Some(bucket) => {
+                        let bucket = bucket.lock();
+                        let reset_in = (bucket.reset - now).max(0);
+                        limited |= bucket.remaining == 0 && reset_in > 0;
+
+                        write!(s,
+                               "**{}** bucket: {}/{} remaining, resets in {}s\n",
+                               name,
+                               bucket.remaining,
+                               bucket.limit,
+                               reset_in)
+                    },
+                    None => write!(s, "**{}** bucket: no rate limit seen yet\n", name),
+                };
+            }
+        }
+
+        // The global lock is only held while a global rate limit is waited out.
+        if GLOBAL.try_lock().is_none() {
+            s.push_str(":warning: Currently globally rate limited\n");
+        } else if limited {
+            s.push_str(":warning: Currently rate limited on at least one bucket\n");
+        }
+
+        reply.edit(|m| m.content(&s)).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct EmojiCommand;
 
-        let end = Utc::now();
-        let ms = {
-            let end_ms = end.timestamp_subsec_millis() as i64;
-            let start_ms = start.timestamp_subsec_millis() as i64;
+impl Command for EmojiCommand {
+    fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        let arg = args.full().trim();
+
+        if arg.is_empty() {
+            return Err(Error::BadArgument("An emoji must be given".to_owned()).into());
+        }
+
+        if let Some(emoji) = misc::custom_emojis(arg).into_iter().next() {
+            let ext = if emoji.animated { "gif" } else { "png" };
+            let url = format!("https://cdn.discordapp.com/emojis/{}.{}", emoji.id, ext);
+            let created = format!("{} UTC", EmojiId(emoji.id).created_at().format("%Y-%m-%d %H:%M:%S"));
+            let guild = CACHE.read()
+                .guilds
+                .values()
+                .find(|guild| guild.read().emojis.contains_key(&EmojiId(emoji.id)))
+                .map_or_else(|| "Unknown".to_owned(), |guild| {
+                    let guild = guild.read();
+
+                    format!("{} ({})", guild.name, guild.id)
+                });
+
+            msg.channel_id.send_message(|m| m
+                .embed(|e| e
+                    .title(&format!(":{}:", emoji.name))
+                    .description(&format!("[Download]({})", url))
+                    .field("ID", &emoji.id.to_string(), true)
+                    .field("Animated", if emoji.animated { "Yes" } else { "No" }, true)
+                    .field("Created", &created, true)
+                    .field("Server", &guild, true)
+                    .image(&url))).map_err(Error::from)?;
+
+            return Ok(());
+        }
+
+        if arg.is_ascii() || arg.chars().count() > 16 {
+            return Err(Error::BadArgument("That doesn't look like an emoji".to_owned()).into());
+        }
+
+        let codepoints = arg.chars().map(|c| format!("U+{:04X}", c as u32)).collect::<Vec<_>>();
 
-            end_ms - start_ms
+        // Twemoji leaves variation selectors out of its file names, except in
+        // sequences joined with a ZWJ.
+        let zwj = arg.contains('\u{200D}');
+        let file = arg.chars()
+            .filter(|c| zwj || *c != '\u{FE0F}')
+            .map(|c| format!("{:x}", c as u32))
+            .collect::<Vec<_>>()
+            .join("-");
+        let url = format!("https://cdn.jsdelivr.net/gh/twitter/twemoji@latest/assets/72x72/{}.png", file);
+        let encoded = arg.bytes().map(|b| format!("%{:02X}", b)).collect::<String>();
+        let lookup = format!("[Emojipedia](https://emojipedia.org/search/?q={})", encoded);
+
+        // Sequences are named by their parts, leaving out the joiners and
+        // variation selectors that don't have meaningful names.
+        let names = arg.chars()
+            .filter(|c| *c != '\u{200D}' && *c != '\u{FE0F}')
+            .map(|c| match unicode_names2::name(c) {
+                Some(name) => name.to_string().to_lowercase(),
+                None => "unknown".to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join(" + ");
+
+        msg.channel_id.send_message(|m| m
+            .embed(|e| e
+                .title(arg)
+                .description(&lookup)
+                .field("Name", &names, true)
+                .field("Codepoints", &codepoints.join(" "), true)
+                .image(&url))).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct EmojiStatsCommand;
+
+impl Command for EmojiStatsCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let unused_only = args.single::<String>().map(|x| x == "unused").unwrap_or(false);
+
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
         };
-        let diff = ((end.timestamp() - start.timestamp()) * 1000) + ms;
 
-        let _ = msg.edit(|m| m.content(&format!("Pong! `[{}ms]`", diff)));
+        let (guild_id, emojis) = {
+            let guild = guild.read();
+            let emojis = guild.emojis.values().cloned().collect::<Vec<_>>();
+
+            (guild.id, emojis)
+        };
+
+        if emojis.is_empty() {
+            return Err(Error::NotFound("any custom emojis in this server".to_owned()).into());
+        }
+
+        let now = Utc::now();
+
+        let mut s = {
+            let data = ctx.data.lock();
+            let usage = data.get::<EmojiUsageStore>().unwrap();
+            let guild_usage = usage.guilds.get(&guild_id.0);
+
+            let mut rows = emojis.iter()
+                .map(|emoji| {
+                    let stat = guild_usage.and_then(|usage| usage.emojis.get(&emoji.id.0));
+
+                    (stat.map_or(0, |stat| stat.count), stat.map(|stat| stat.last_used), emoji)
+                })
+                .filter(|&(count, _, _)| !unused_only || count == 0)
+                .collect::<Vec<_>>();
+            rows.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.2.name.cmp(&b.2.name)));
+
+            let mut s = match guild_usage {
+                Some(usage) => format!("Emoji usage since {} UTC:\n", &usage.since.to_rfc3339()[..19]),
+                None => "No emoji usage has been recorded yet:\n".to_owned(),
+            };
+
+            if rows.is_empty() {
+                s.push_str("Every emoji has been used");
+            }
+
+            for (count, last_used, emoji) in rows {
+                let prefix = if emoji.animated { "a" } else { "" };
+                let last_used = last_used.map_or_else(|| "never used".to_owned(), |at| {
+                    format!("last used {} ago", duration::format(now - at))
+                });
+
+                let _ = write!(s,
+                               "<{}:{}:{}> `{}`: {} uses, {}\n",
+                               prefix,
+                               emoji.name,
+                               emoji.id,
+                               emoji.name,
+                               count,
+                               last_used);
+            }
+
+            s
+        };
+
+        if s.len() > 2000 {
+            let end = s[..1997].rfind('\n').unwrap_or(0);
+            s.truncate(end);
+            s.push_str("\n...");
+        }
+
+        msg.channel_id.say(&s).map_err(Error::from)?;
 
         Ok(())
     }
@@ -96,134 +452,374 @@ impl Command for RpingCommand {
 pub struct GpingCommand;
 
 impl Command for GpingCommand {
-    fn execute(&self, _: &mut Context, msg: &Message, _: Args) -> CommandResult {
-        let _ = msg.channel_id.say("N/A");
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let all = args.single::<String>().map(|x| x == "all").unwrap_or(false);
+
+        let (manager, health) = {
+            let data = ctx.data.lock();
+            let manager = Arc::clone(data.get::<ShardManagerContainer>().unwrap());
+            let health = data.get::<ShardHealth>().unwrap().clone();
+
+            (manager, health)
+        };
+
+        // Copy out what's needed so that neither lock is held while sending.
+        let mut shards = {
+            let manager = manager.lock();
+            let runners = manager.runners.lock();
+
+            runners.iter()
+                .map(|(id, runner)| (id.0, runner.latency, runner.stage))
+                .collect::<Vec<_>>()
+        };
+
+        if !all {
+            let content = match shards.iter().find(|&&(id, _, _)| id == ctx.shard_id) {
+                Some(&(_, latency, _)) => format!("Pong! Shard {} heartbeat latency: `{}`",
+                                                  ctx.shard_id,
+                                                  format_latency(latency)),
+                None => format!("Shard {} isn't running", ctx.shard_id),
+            };
+
+            msg.channel_id.say(&content).map_err(Error::from)?;
+
+            return Ok(());
+        }
+
+        shards.sort_by_key(|&(id, _, _)| id);
+
+        let mut s = "```\nShard | Latency  | Last ACK            | Stage\n".to_owned();
+
+        for (id, latency, stage) in shards {
+            let last_ack = health.get(&id)
+                .and_then(|status| status.last_ack)
+                .map_or_else(|| "N/A".to_owned(), |at| at.to_rfc3339()[..19].to_owned());
+
+            let _ = write!(s,
+                           "{:<5} | {:<8} | {:<19} | {:?}\n",
+                           id,
+                           format_latency(latency),
+                           last_ack,
+                           stage);
+        }
+
+        s.push_str("```");
+
+        msg.channel_id.say(&s).map_err(Error::from)?;
 
         Ok(())
     }
 }
 
-pub struct RoleInfoCommand;
+pub struct PermsCommand;
 
-impl Command for RoleInfoCommand {
-    fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
-        let cache = CACHE.read();
+impl Command for PermsCommand {
+    fn execute(&self, _: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
+        };
+        let guild_id = guild.read().id;
 
-        let guild_id = match cache.guild_channel(msg.channel_id) {
-            Some(channel) => channel.read().guild_id,
-            None => {
-                let _ = msg.channel_id.say("Error finding channel data");
+        let mut words = vec![];
 
-                return Ok(());
-            },
-        };
+        while let Ok(word) = args.single_quoted::<String>() {
+            words.push(word);
+        }
 
-        let guild = match cache.guild(guild_id) {
-            Some(guild) => guild,
-            None => {
-                let _ = msg.channel_id.say("Could not find server data");
+        let why = words.first().map_or(false, |word| word == "why");
 
-                return Ok(());
-            },
-        };
+        if why {
+            words.remove(0);
+        }
 
-        // It's a pretty inexpensive operation to clone a role, so it should save
-        // keeping the cache unlocked.
-        let role = if !msg.mention_roles.is_empty() {
-            let id = unsafe { msg.mention_roles.get_unchecked(0) };
+        let usage = "Usage: `perms <member> [channel]` or `perms why <member> <permission> [channel]`";
+        let min_words = if why { 2 } else { 1 };
 
-            match guild.read().roles.values().find(|r| r.id == *id).cloned() {
-                Some(role) => role,
-                None => {
-                    warn!("Couldn't find r{} for c{}", id, msg.channel_id);
+        if words.len() < min_words || words.len() > min_words + 1 {
+            return Err(Error::BadArgument(usage.to_owned()).into());
+        }
 
-                    let _ = msg.channel_id.say("Mentioned role not found; error logged");
+        let member = resolver::member(guild_id, &words[0]).or_error("member", resolver::describe_member)?;
 
-                    return Ok(());
+        let bit = if why {
+            match permissions::parse(&words[1]) {
+                Some(bit) => Some(bit),
+                None => {
+                    return Err(Error::NotFound("that permission".to_owned()).into());
                 },
             }
-        } else if !args.is_empty() {
-            let role_name = args.full();
+        } else {
+            None
+        };
 
-            match guild.read().roles.values().find(|r| r.name == role_name).cloned() {
-                Some(role) => role,
-                None => {
-                    let id = match role_name.parse::<u64>() {
-                        Ok(id) => id,
-                        Err(_) => {
-                            let _ = msg.channel_id.say("Role not found by name");
+        let resolved = match words.get(min_words) {
+            Some(arg) => resolver::channel(&guild.read(), msg.author.id, arg),
+            None => guild.read()
+                .channels
+                .get(&msg.channel_id)
+                .map_or(Resolved::NotFound, |channel| Resolved::Found(channel.read().clone())),
+        };
 
-                            return Ok(());
-                        },
+        let channel = resolved.or_error("channel", resolver::describe_channel)?;
+
+        let explanation = Explanation::compute(&guild.read(), &member, Some(&channel));
+        let tag = member.user.read().tag();
+
+        let mut description = String::new();
+
+        let title = match bit {
+            Some(bit) => {
+                let name = permissions::names(bit)[0];
+
+                for step in &explanation.steps {
+                    let effect = if step.deny & bit != 0 && step.allow & bit == 0 {
+                        "denies"
+                    } else if step.allow & bit != 0 {
+                        "allows"
+                    } else {
+                        "no effect"
                     };
+                    let state = if step.after & bit != 0 { "granted" } else { "denied" };
 
-                    match guild.read().roles.values().find(|r| r.id == id).cloned() {
-                        Some(role) => role,
-                        None => {
-                            warn!("Couldn't find r{} for c{}", id, msg.channel_id);
-                            let _ = msg.channel_id.say("Role not found; error logged");
+                    let _ = write!(description, "- {}: {} ({})\n", step.description, effect, state);
+                }
+
+                let verdict = match (explanation.value & bit != 0, explanation.decider(bit)) {
+                    (true, Some(step)) => format!("**Granted** by {}", step.description),
+                    (false, Some(step)) => format!("**Denied** by {}", step.description),
+                    (_, None) => "**Denied**: not granted by any role".to_owned(),
+                };
+                description.push_str(&verdict);
+
+                format!("Why {} has {} in #{}", tag, name, channel.name)
+            },
+            None => {
+                let mut granted = String::new();
+                let mut denied = String::new();
+                let mut ungranted = vec![];
 
-                            return Ok(());
+                for &(bit, name) in permissions::PERMISSIONS.iter() {
+                    match (explanation.value & bit != 0, explanation.decider(bit)) {
+                        (true, Some(step)) => {
+                            let _ = write!(granted, "+ {} ({})\n", name, step.description);
+                        },
+                        (false, Some(step)) => {
+                            let _ = write!(denied, "- {} ({})\n", name, step.description);
                         },
+                        _ => ungranted.push(name),
                     }
-                },
-            }
-        } else {
-            let _ = msg.channel_id.say("A role name must be given or mentioned");
+                }
 
-            return Ok(());
+                if !granted.is_empty() {
+                    let _ = write!(description, "**Granted**\n{}", granted);
+                }
+
+                if !denied.is_empty() {
+                    let _ = write!(description, "**Denied**\n{}", denied);
+                }
+
+                if !ungranted.is_empty() {
+                    let _ = write!(description, "**Not granted by any role**\n{}", ungranted.join(", "));
+                }
+
+                format!("Permissions for {} in #{}", tag, channel.name)
+            },
         };
 
+        truncate_lines(&mut description, 2048);
+
+        msg.channel_id.send_message(|m| m
+            .embed(|e| e
+                .title(&title)
+                .description(&description))).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct RoleInfoCommand;
+
+impl Command for RoleInfoCommand {
+    fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
+        };
+
+        if args.is_empty() {
+            return Err(Error::BadArgument("A role name must be given or mentioned".to_owned()).into());
+        }
+
+        // The resolver clones the role, so the cache isn't kept locked while
+        // the reply is sent.
+        let resolved = resolver::role(&guild.read(), args.full());
+
+        let role = resolved.or_error("role", resolver::describe_role)?;
+
         let description = {
             let mut s = "**Permissions**:".to_owned();
 
-            let p = &role.permissions;
-            let permissions = permissions! { p;
-                add_reactions "Add Reactions",
-                administrator "Administrator",
-                attach_files "Attach Files",
-                ban_members "Ban Members",
-                change_nickname "Change Nickname",
-                connect "Connect",
-                create_invite "Create Invite",
-                deafen_members "Deafen Members",
-                embed_links "Embed Links",
-                external_emojis "External Emojis",
-                kick_members "Kick Members",
-                manage_channels "Manage Channels",
-                manage_emojis "Manage Emojis",
-                manage_guild "Manage Guild",
-                manage_messages "Manage Messages",
-                manage_nicknames "Manage Nicknames",
-                manage_roles "Manage Roles",
-                manage_webhooks "Manage Webhooks",
-                mention_everyone "Mention Everyone",
-                move_members "Move Members",
-                mute_members "Mute Members",
-                read_message_history "Read Message History",
-                read_messages "Read Messages",
-                send_messages "Send Messages",
-                send_tts_messages "Send TTS Messages",
-                speak "Speak",
-                use_external_emojis "Use External Emojis",
-                use_vad "Use VAD",
-            };
-
-            s.push_str(&permissions.join(", "));
+            s.push_str(&permissions::names(role.permissions.bits()).join(", "));
 
             s
         };
         let hoisted = if role.hoist { "Yes" } else { "No" };
         let mentionable = if role.mentionable { "Yes" } else { "No" };
 
-        let _ = msg.channel_id.send_message(|m| m
+        msg.channel_id.send_message(|m| m
             .embed(|e| e
                 .title(&format!("Role info for {} ({})", role.name, role.id.0))
                 .description(&description)
                 .colour(role.colour)
                 .field("Hoisted", hoisted, true)
                 .field("Position", &role.position.to_string(), true)
-                .field("Mentionable", mentionable, true)));
+                .field("Mentionable", mentionable, true))).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct ServerInfoCommand;
+
+impl Command for ServerInfoCommand {
+    fn execute(&self, _: &mut Context, msg: &Message, _: Args) -> CommandResult {
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
+        };
+        let guild = guild.read().clone();
+
+        let owner = match CACHE.read().user(guild.owner_id) {
+            Some(user) => user.read().tag(),
+            None => guild.owner_id.to_string(),
+        };
+        let created = format!("{} UTC", guild.id.created_at().format("%Y-%m-%d %H:%M:%S"));
+
+        let bots = guild.members.values().filter(|m| m.user.read().bot).count();
+        let online = guild.presences
+            .values()
+            .filter(|p| p.status != OnlineStatus::Offline && p.status != OnlineStatus::Invisible)
+            .count();
+        let members = format!("{} total\n{} online\n{} bots (of {} cached)",
+                              guild.member_count,
+                              online,
+                              bots,
+                              guild.members.len());
+
+        let (mut text, mut voice, mut categories) = (0, 0, 0);
+
+        for channel in guild.channels.values() {
+            match channel.read().kind {
+                ChannelType::Category => categories += 1,
+                ChannelType::Text => text += 1,
+                ChannelType::Voice => voice += 1,
+                _ => {},
+            }
+        }
+
+        let channels = format!("{} text\n{} voice\n{} categories", text, voice, categories);
+        let emojis = {
+            let animated = guild.emojis.values().filter(|e| e.animated).count();
+
+            format!("{} ({} animated)", guild.emojis.len(), animated)
+        };
+        let verification = match guild.verification_level {
+            VerificationLevel::None => "None",
+            VerificationLevel::Low => "Low",
+            VerificationLevel::Medium => "Medium",
+            VerificationLevel::High => "High",
+            VerificationLevel::Higher => "Highest",
+        };
+        let features = if guild.features.is_empty() {
+            "None".to_owned()
+        } else {
+            guild.features.join(", ")
+        };
+
+        msg.channel_id.send_message(|m| m
+            .embed(|mut e| {
+                e = e.title(&format!("Server info for {}", guild.name))
+                    .field("ID", &guild.id.to_string(), true)
+                    .field("Owner", &owner, true)
+                    .field("Region", &guild.region, true)
+                    .field("Created", &created, true)
+                    .field("Members", &members, true)
+                    .field("Channels", &channels, true)
+                    .field("Roles", &guild.roles.len().to_string(), true)
+                    .field("Emojis", &emojis, true)
+                    .field("Verification Level", verification, true)
+                    .field("Features", &features, false);
+
+                if let Some(icon_url) = guild.icon_url() {
+                    e = e.thumbnail(&icon_url);
+                }
+
+                e
+            })).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct SnowflakeCommand;
+
+impl Command for SnowflakeCommand {
+    fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        const MAX_IDS: usize = 10;
+
+        let words = args.multiple::<String>().unwrap_or_default();
+
+        if words.is_empty() || words.len() > MAX_IDS {
+            return Err(Error::BadArgument(format!("Usage: `snowflake <id...>` with 1 to {} IDs", MAX_IDS)).into());
+        }
+
+        let mut ids = vec![];
+
+        for word in &words {
+            match resolver::parse_snowflake(word) {
+                Some(id) => ids.push(id),
+                None => {
+                    return Err(Error::BadArgument(format!("`{}` is not an ID", word)).into());
+                },
+            }
+        }
+
+        let now = Utc::now();
+        let snowflakes = ids.iter().map(|id| Snowflake::decode(*id)).collect::<Vec<_>>();
+
+        let mut s = String::new();
+
+        for (id, snowflake) in ids.iter().zip(&snowflakes) {
+            let _ = write!(s,
+                           "**{}**\nCreated: {} UTC ({} ago)\nWorker: {}, Process: {}, Increment: {}\n",
+                           id,
+                           snowflake.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+                           duration::format(now - snowflake.timestamp),
+                           snowflake.worker_id,
+                           snowflake.process_id,
+                           snowflake.increment);
+        }
+
+        if snowflakes.len() > 1 {
+            s.push_str("\n**Time between**\n");
+
+            for (i, pair) in snowflakes.windows(2).enumerate() {
+                let diff = pair[1].timestamp - pair[0].timestamp;
+                let sign = if diff < Duration::zero() { "-" } else { "" };
+                let abs = if sign.is_empty() { diff } else { -diff };
+
+                let _ = write!(s,
+                               "{} → {}: {}{} ({}ms)\n",
+                               ids[i],
+                               ids[i + 1],
+                               sign,
+                               duration::format(abs),
+                               diff.num_milliseconds());
+            }
+        }
+
+        msg.channel_id.say(&s).map_err(Error::from)?;
 
         Ok(())
     }
@@ -232,29 +828,75 @@ impl Command for RoleInfoCommand {
 pub struct UptimeCommand;
 
 impl Command for UptimeCommand {
-    fn execute(&self, ctx: &mut Context, msg: &Message, _: Args) -> CommandResult {
-        let (boot, conn) = {
-            let data = ctx.data.lock();
-            let uptimes = data.get::<ShardUptime>().unwrap();
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let all = args.single::<String>().map(|x| x == "all").unwrap_or(false);
+        let now = Utc::now();
+        let name = CACHE.read().user.name.clone();
 
-            if let Some(entry) = uptimes.get(&ctx.shard_id) {
-                let boot = entry.boot.to_rfc3339()[..19].to_owned();
-                let conn = entry.connection.to_rfc3339()[..19].to_owned();
+        let data = ctx.data.lock();
+        let uptimes = data.get::<ShardUptime>().unwrap();
 
-                (boot, conn)
-            } else {
-                ("N/A".to_owned(), "N/A".to_owned())
+        if all {
+            let mut ids = uptimes.keys().collect::<Vec<_>>();
+            ids.sort();
+
+            let mut s = "```\nShard | Connection   | Reconnects | Last Resume\n".to_owned();
+
+            for id in ids {
+                let entry = &uptimes[id];
+                let last_resume = entry.last_resume
+                    .map_or_else(|| "Never".to_owned(), |at| format!("{} ago", duration::format(now - at)));
+
+                let _ = write!(s,
+                               "{:<5} | {:<12} | {:<10} | {}\n",
+                               id,
+                               duration::format(now - entry.connection),
+                               entry.reconnects,
+                               last_resume);
             }
+
+            s.push_str("```");
+
+            drop(data);
+
+            msg.channel_id.say(&s).map_err(Error::from)?;
+
+            return Ok(());
+        }
+
+        let (boot, conn, reconnects, last_resume) = match uptimes.get(&ctx.shard_id) {
+            Some(entry) => {
+                let last_resume = entry.last_resume.map_or_else(|| "Never".to_owned(), |at| {
+                    format!("{} UTC ({} ago)", &at.to_rfc3339()[..19], duration::format(now - at))
+                });
+
+                (duration::format(now - entry.boot),
+                 duration::format(now - entry.connection),
+                 entry.reconnects.to_string(),
+                 last_resume)
+            },
+            None => ("N/A".to_owned(), "N/A".to_owned(), "N/A".to_owned(), "N/A".to_owned()),
         };
 
-        let name = CACHE.read().user.name.clone();
+        let (week, month) = {
+            let history = data.get::<BootHistoryStore>().unwrap();
+
+            (format!("{:.2}%", history.uptime_percent(7)),
+             format!("{:.2}%", history.uptime_percent(30)))
+        };
+
+        drop(data);
 
-        let _ = msg.channel_id.send_message(|m| m
+        msg.channel_id.send_message(|m| m
             .embed(|e| e
                 .colour(0x8700B2)
-                .title(&format!("Uptime for {}", name))
+                .title(&format!("Uptime for {} (shard {})", name, ctx.shard_id))
                 .field("Since Boot", &boot, true)
-                .field("Current Connection", &conn, true)));
+                .field("Current Connection", &conn, true)
+                .field("Reconnects", &reconnects, true)
+                .field("Last Resume", &last_resume, true)
+                .field("Last 7 Days", &week, true)
+                .field("Last 30 Days", &month, true))).map_err(Error::from)?;
 
         Ok(())
     }
@@ -263,37 +905,83 @@ impl Command for UptimeCommand {
 pub struct UserInfoCommand;
 
 impl Command for UserInfoCommand {
-    fn execute(&self, _: &mut Context, msg: &Message, _: Args) -> CommandResult {
-        let member = {
-            let guild_id = CACHE.read()
-                .guild_channel(msg.channel_id)
-                .map(|c| c.read().guild_id);
-
-            if let Some(guild_id) = guild_id {
-                // Clone so the lock can be dropped ASAP.
-                match CACHE.read().guilds.get(&guild_id) {
-                    Some(guild) => guild.read().members.get(&msg.author.id).cloned(),
-                    None => None,
-                }
-            } else {
-                None
-            }
+    fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        let guild = msg.guild();
+        // Clone so the lock can be dropped ASAP.
+        let guild = guild.map(|guild| guild.read().clone());
+
+        let user = if args.is_empty() {
+            msg.author.clone()
+        } else {
+            resolver::user(msg.guild_id(), args.full()).or_error("user", resolver::describe_user)?
+        };
+
+        let member = guild.as_ref().and_then(|guild| guild.members.get(&user.id));
+        let presence = match guild {
+            Some(ref guild) => guild.presences.get(&user.id).cloned(),
+            None => CACHE.read().presences.get(&user.id).cloned(),
         };
-        let discriminator = msg.author.discriminator.to_string();
 
-        let _ = msg.channel_id.send_message(|m| m
+        let created_at = user.id.created_at();
+        let created = format!("{} UTC ({} days ago)",
+                              created_at.format("%Y-%m-%d %H:%M:%S"),
+                              (Utc::now().naive_utc() - created_at).num_days());
+        let discriminator = format!("{:04}", user.discriminator);
+        let presence = presence.map_or_else(|| "Offline".to_owned(), |presence| {
+            let status = match presence.status {
+                OnlineStatus::DoNotDisturb => "Do Not Disturb",
+                OnlineStatus::Idle => "Idle",
+                OnlineStatus::Invisible => "Invisible",
+                OnlineStatus::Offline => "Offline",
+                OnlineStatus::Online => "Online",
+            };
+
+            match presence.game {
+                Some(game) => {
+                    let kind = match game.kind {
+                        GameType::Listening => "Listening to",
+                        GameType::Playing => "Playing",
+                        GameType::Streaming => "Streaming",
+                    };
+
+                    format!("{}; {} **{}**", status, kind, game.name)
+                },
+                None => status.to_owned(),
+            }
+        });
+
+        msg.channel_id.send_message(|m| m
             .embed(|mut e| {
-                e = e.title(&format!("User info for {}", msg.author.name))
-                    .field("ID", &msg.author.id.to_string(), true)
-                    .field("Discriminator", &discriminator, true);
+                e = e.title(&format!("User info for {}", user.tag()))
+                    .field("ID", &user.id.to_string(), true)
+                    .field("Discriminator", &discriminator, true)
+                    .field("Created", &created, true)
+                    .field("Presence", &presence, true);
 
-                if let Some(ref member) = member {
+                if let Some(avatar_url) = user.avatar_url() {
+                    e = e.thumbnail(&avatar_url);
+                }
+
+                if let (Some(guild), Some(member)) = (guild.as_ref(), member) {
                     if let Some(joined_at) = member.joined_at {
                         let formatted = format!("{} UTC", &joined_at.to_rfc3339()[..19]);
 
                         e = e.field("Joined", &formatted, true);
                     }
 
+                    // The position is only known when every member is cached.
+                    let position = if guild.members.len() as u64 >= guild.member_count {
+                        join_position(guild, user.id)
+                    } else {
+                        None
+                    };
+
+                    if let Some(position) = position {
+                        let formatted = format!("{} of {}", position, guild.members.len());
+
+                        e = e.field("Join Position", &formatted, true);
+                    }
+
                     let nick = member.nick.clone()
                         .map_or_else(|| "\u{200b}".to_owned(), |v| v.clone());
 
@@ -304,11 +992,175 @@ impl Command for UserInfoCommand {
 
                         e = e.colour(colour).field("Colour", &s, true);
                     }
+
+                    let mut roles = member.roles
+                        .iter()
+                        .filter_map(|id| guild.roles.get(id))
+                        .collect::<Vec<_>>();
+                    roles.sort_by(|a, b| b.position.cmp(&a.position));
+
+                    // Boosting gives members the guild's managed booster role,
+                    // and there's nothing else on the member to go by. It can
+                    // only be found by name, so this is left out when no such
+                    // role is found, e.g. because it was renamed.
+                    let is_booster_role = |r: &Role| r.managed && r.name.contains("Booster");
+
+                    if guild.roles.values().any(|r| is_booster_role(r)) {
+                        let boosting = roles.iter().any(|r| is_booster_role(*r));
+                        e = e.field("Boosting (guessed)", if boosting { "Yes" } else { "No" }, true);
+                    }
+
+                    let mut roles = roles.iter()
+                        .map(|r| format!("<@&{}>", r.id))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+
+                    if roles.is_empty() {
+                        roles.push_str("None");
+                    } else if roles.len() > 1024 {
+                        let end = roles[..1021].rfind(' ').unwrap_or(0);
+                        roles.truncate(end);
+                        roles.push_str("...");
+                    }
+
+                    e = e.field(&format!("Roles ({})", member.roles.len()), &roles, false);
+
+                    let p = guild.member_permissions(user.id);
+                    let permissions = permissions::names(p.bits() & KEY_PERMISSIONS);
+
+                    if !permissions.is_empty() {
+                        e = e.field("Key Permissions", &permissions.join(", "), false);
+                    }
                 }
 
                 e
-            }));
+            })).map_err(Error::from)?;
 
         Ok(())
     }
 }
+
+/// Shows help, leaving out the commands that are disabled where it was used.
+pub fn help(ctx: &mut Context, msg: &Message, groups: HashMap<String, Arc<CommandGroup>>, args: Args)
+    -> CommandResult {
+    let guild_id = msg.guild_id().map(|id| id.0);
+
+    let groups = {
+        let data = ctx.data.lock();
+        let toggles = data.get::<CommandToggleStore>().unwrap();
+
+        groups.into_iter().filter_map(|(name, group)| {
+            let mut group = (*group).clone();
+            group.commands.retain(|command, _| {
+                let command = commands::canonical_name(command);
+
+                !commands::is_toggleable(command)
+                    || toggles.enabled(guild_id, msg.channel_id.0, command, commands::group_of(command))
+            });
+
+            if group.commands.is_empty() {
+                None
+            } else {
+                Some((name, Arc::new(group)))
+            }
+        }).collect()
+    };
+
+    help_commands::with_embeds(ctx, msg, groups, args)
+}
+
+/// The 1-indexed position of the member in the order that the guild's cached
+/// members joined.
+fn join_position(guild: &Guild, user_id: UserId) -> Option<usize> {
+    let mut joins = guild.members
+        .values()
+        .filter_map(|m| m.joined_at.map(|joined_at| (joined_at, m.user.read().id)))
+        .collect::<Vec<_>>();
+    joins.sort();
+
+    joins.iter().position(|&(_, id)| id == user_id).map(|pos| pos + 1)
+}
+
+/// Retrieves a channel's slowmode, in seconds.
+///
+/// serenity's channel model predates slowmode and its request functions only
+/// return models, so the raw channel object is requested separately. The
+/// request still defers to serenity's rate limiter: it waits out a global
+/// rate limit, and is skipped while the channel's bucket is exhausted.
+fn slowmode(channel_id: ChannelId) -> Option<u64> {
+    let token = match env::var("DISCORD_TOKEN") {
+        Ok(token) => token,
+        Err(_) => return None,
+    };
+
+    drop(GLOBAL.lock());
+
+    if let Some(bucket) = ROUTES.lock().get(&Route::ChannelsId(channel_id.0)) {
+        let bucket = bucket.lock();
+
+        if bucket.remaining == 0 && bucket.reset > Utc::now().timestamp() {
+            return None;
+        }
+    }
+
+    let url = format!("https://discordapp.com/api/v6/channels/{}", channel_id);
+    let res = Client::new()
+        .get(&url)
+        .header(Authorization(format!("Bot {}", token)))
+        .send();
+
+    let value = match res.and_then(|mut res| res.json::<Value>()) {
+        Ok(value) => value,
+        Err(why) => {
+            warn!("Err retrieving channel {}: {:?}", channel_id, why);
+
+            return None;
+        },
+    };
+
+    value.get("rate_limit_per_user").and_then(Value::as_u64)
+}
+
+/// Shortens text to at most `max` bytes, cutting after the last whole line
+/// that fits.
+fn truncate_lines(s: &mut String, max: usize) {
+    if s.len() <= max {
+        return;
+    }
+
+    let mut end = max - 3;
+
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let end = s[..end].rfind('\n').unwrap_or(0);
+    s.truncate(end);
+    s.push_str("\n...");
+}
+
+fn elapsed_ms(start: Instant) -> u64 {
+    let elapsed = start.elapsed();
+
+    elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_nanos()) / 1_000_000
+}
+
+/// Formats a row of min, median, 95th percentile and max latency.
+fn latency_row(name: &str, samples: &mut [u64]) -> String {
+    if samples.is_empty() {
+        return format!("{:<4} | {:<7} | N/A\n", name, 0);
+    }
+
+    samples.sort();
+
+    let len = samples.len();
+    let p95 = ((len as f64 * 0.95).ceil() as usize).max(1) - 1;
+
+    format!("{:<4} | {:<7} | {:<6} | {:<6} | {:<6} | {}\n",
+            name,
+            len,
+            format!("{}ms", samples[0]),
+            format!("{}ms", samples[len / 2]),
+            format!("{}ms", samples[p95]),
+            format!("{}ms", samples[len - 1]))
+}
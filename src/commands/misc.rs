@@ -119,7 +119,7 @@ impl Command for AesCapsCommand {
         let modifiers = [AestheticMode::Bold, AestheticMode::Caps];
 
         if let Some(content) = aestheticize(args.full().to_owned(), &modifiers) {
-            let _ = msg.channel_id.say(&content);
+            msg.channel_id.say(&content).map_err(Error::from)?;
         }
 
         Ok(())
@@ -131,7 +131,7 @@ pub struct AesCommand;
 impl Command for AesCommand {
     fn execute(&self, _: &mut Context, msg: &Message, args: Args) -> CommandResult {
         if let Some(content) = aestheticize(args.full().to_owned(), &[]) {
-            let _ = msg.channel_id.say(&content);
+            msg.channel_id.say(&content).map_err(Error::from)?;
         }
 
         Ok(())
@@ -214,15 +214,13 @@ impl Command for PiCommand {
                 if length <= 1000 {
                     length + 2
                 } else {
-                    let _ = msg.channel_id.say("Must be at most 1000");
-
-                    return Ok(());
+                    return Err(Error::BadArgument("Must be at most 1000".to_owned()).into());
                 }
             },
             Err(_) => 102,
         };
 
-        let _ = msg.channel_id.say(&PI[..length]);
+        msg.channel_id.say(&PI[..length]).map_err(Error::from)?;
 
         Ok(())
     }
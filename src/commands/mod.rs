@@ -1,6 +1,99 @@
 pub mod conversation;
 pub mod media;
 pub mod meta;
+pub mod moderation;
 pub mod misc;
 pub mod owner;
 pub mod random;
+
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+/// How many commands were registered through `toggleable`.
+static REGISTERED: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Every command that can be toggled per guild and channel, with the group
+/// it's registered in.
+///
+/// This mirrors the framework setup in `main.rs`, since hooks are only given
+/// the name a command was invoked by. Owner commands and `toggle` itself are
+/// left out so that nobody can lock themselves out.
+///
+/// Commands are registered through `toggleable` and checked with
+/// `check_toggleable`, so the two can't drift apart.
+pub static TOGGLEABLE: [(&'static str, Option<&'static str>); 27] = [
+    ("8ball", Some("Luck")),
+    ("choose", Some("Luck")),
+    ("coinflip", Some("Luck")),
+    ("roll", Some("Luck")),
+    ("roulette", Some("Luck")),
+    ("anime", Some("Media")),
+    ("avatar", Some("Meta")),
+    ("channelinfo", Some("Meta")),
+    ("emoji", Some("Meta")),
+    ("emojistats", Some("Meta")),
+    ("perms", Some("Meta")),
+    ("roleinfo", Some("Meta")),
+    ("serverinfo", Some("Meta")),
+    ("snowflake", Some("Meta")),
+    ("uptime", Some("Meta")),
+    ("userinfo", Some("Meta")),
+    ("aes", Some("Misc")),
+    ("aescaps", Some("Misc")),
+    ("aesthetic", Some("Misc")),
+    ("aestheticcaps", Some("Misc")),
+    ("hello", Some("Misc")),
+    ("mfw", Some("Misc")),
+    ("pi", Some("Misc")),
+    ("sticky", Some("Moderation")),
+    ("temprole", Some("Moderation")),
+    ("modping", None),
+    ("udefined", None),
+];
+
+/// The groups whose commands can be toggled together.
+pub static GROUPS: [&'static str; 5] = ["Luck", "Media", "Meta", "Misc", "Moderation"];
+
+/// Maps an alias to the name of the command it's an alias of.
+pub fn canonical_name(name: &str) -> &str {
+    match name {
+        "animu" => "anime",
+        "guildinfo" => "serverinfo",
+        "id" => "snowflake",
+        "me" => "userinfo",
+        "pingmod" => "modping",
+        name => name,
+    }
+}
+
+/// The group a command is in, or `None` if it's ungrouped or unknown.
+pub fn group_of(name: &str) -> Option<&'static str> {
+    TOGGLEABLE.iter().find(|&&(n, _)| n == name).and_then(|&(_, group)| group)
+}
+
+pub fn is_toggleable(name: &str) -> bool {
+    TOGGLEABLE.iter().any(|&(n, _)| n == name)
+}
+
+/// Returns the name of a command that's being registered, panicking if it
+/// isn't listed in `TOGGLEABLE` under the same group.
+pub fn toggleable(name: &'static str, group: Option<&str>) -> &'static str {
+    assert!(is_toggleable(name) && group_of(name) == group,
+            "'{}' in group {:?} is registered but not listed as toggleable",
+            name,
+            group);
+
+    REGISTERED.fetch_add(1, Ordering::SeqCst);
+
+    name
+}
+
+/// Panics if any command in `TOGGLEABLE` wasn't registered through
+/// `toggleable`.
+pub fn check_toggleable() {
+    let registered = REGISTERED.load(Ordering::SeqCst);
+
+    assert!(registered == TOGGLEABLE.len(),
+            "{} commands are listed as toggleable but {} were registered",
+            TOGGLEABLE.len(),
+            registered);
+}
@@ -0,0 +1,424 @@
+use chrono::{Duration, Utc};
+use std::fmt::Write as FmtWrite;
+use ::actions;
+use ::commands;
+use ::misc::{duration, permissions, ListMode, StickyConfig, TempRole, ToggleRules};
+use ::prelude::*;
+use ::resolver;
+use ::store::{AuditLogStore, CommandToggleStore, StickyRoleStore, TempRoleStore};
+
+/// The longest period sticky roles can be kept for, in days.
+const MAX_STICKY_DAYS: i64 = 365;
+
+pub struct StickyCommand;
+
+impl Command for StickyCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
+        };
+        let guild = guild.read();
+
+        let subcommand = args.single::<String>().unwrap_or_default();
+
+        // Work on a copy, so that looking at the settings or getting the usage
+        // wrong doesn't create or save anything.
+        let mut config = {
+            let data = ctx.data.lock();
+
+            data.get::<StickyRoleStore>()
+                .unwrap()
+                .guilds
+                .get(&guild.id.0)
+                .map_or_else(StickyConfig::default, |guild| guild.config.clone())
+        };
+
+        let (changed, content) = match &subcommand[..] {
+            "" => {
+                let mut s = format!("Sticky roles are **{}**\n",
+                                    if config.enabled { "on" } else { "off" });
+                let _ = write!(s, "Nicknames restored: {}\n", if config.nickname { "yes" } else { "no" });
+                let _ = write!(s, "Period: {} days\n", Duration::seconds(config.period).num_days());
+
+                let mut names = config.roles
+                    .iter()
+                    .map(|id| guild.roles.values()
+                        .find(|r| r.id == *id)
+                        .map_or_else(|| id.to_string(), |r| r.name.clone()))
+                    .collect::<Vec<_>>();
+                names.sort();
+
+                let names = if names.is_empty() { "none".to_owned() } else { names.join(", ") };
+
+                let _ = match config.mode {
+                    ListMode::Allow => write!(s, "Only these roles are sticky: {}", names),
+                    ListMode::Deny => write!(s, "All roles are sticky except: {}", names),
+                };
+
+                (false, s)
+            },
+            "on" | "off" => {
+                config.enabled = subcommand == "on";
+
+                (true, ":ok_hand:".to_owned())
+            },
+            "nick" => match args.single::<String>().as_ref().map(|x| &x[..]) {
+                Ok("on") => {
+                    config.nickname = true;
+
+                    (true, ":ok_hand:".to_owned())
+                },
+                Ok("off") => {
+                    config.nickname = false;
+
+                    (true, ":ok_hand:".to_owned())
+                },
+                _ => return Err(Error::BadArgument("Usage: `sticky nick <on|off>`".to_owned()).into()),
+            },
+            "period" => match args.single::<i64>() {
+                Ok(days) if days > 0 && days <= MAX_STICKY_DAYS => {
+                    config.period = Duration::days(days).num_seconds();
+
+                    (true, ":ok_hand:".to_owned())
+                },
+                _ => {
+                    let usage = format!("Usage: `sticky period <days>` (at most {})", MAX_STICKY_DAYS);
+
+                    return Err(Error::BadArgument(usage).into());
+                },
+            },
+            "mode" => match args.single::<String>().as_ref().map(|x| &x[..]) {
+                Ok("allow") => {
+                    config.mode = ListMode::Allow;
+
+                    (true, ":ok_hand:".to_owned())
+                },
+                Ok("deny") => {
+                    config.mode = ListMode::Deny;
+
+                    (true, ":ok_hand:".to_owned())
+                },
+                _ => return Err(Error::BadArgument("Usage: `sticky mode <allow|deny>`".to_owned()).into()),
+            },
+            "add" | "remove" => {
+                let role = resolver::role(&guild, &rest(args)).or_error("role", resolver::describe_role)?;
+
+                if subcommand == "add" {
+                    config.roles.insert(role.id.0);
+                } else {
+                    config.roles.remove(&role.id.0);
+                }
+
+                (true, ":ok_hand:".to_owned())
+            },
+            _ => {
+                let usage = "Usage: `sticky [on|off|nick|period|mode|add|remove]`";
+
+                return Err(Error::BadArgument(usage.to_owned()).into());
+            },
+        };
+
+        if changed {
+            let mut data = ctx.data.lock();
+            let store = data.get_mut::<StickyRoleStore>().unwrap();
+
+            store.guilds.entry(guild.id.0).or_insert_with(Default::default).config = config;
+            store.save();
+        }
+
+        drop(guild);
+
+        msg.channel_id.say(&content).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct TempRoleCommand;
+
+impl Command for TempRoleCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        const USAGE: &'static str = "Usage: `temprole <member> <role> <duration>`, `temprole list` or `temprole log`";
+        const INVALID_DURATION: &'static str = "Invalid duration; use e.g. `30m`, `12h` or `1d12h`, up to a year";
+
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
+        };
+
+        let mut words = args.multiple::<String>().unwrap_or_default();
+
+        match words.first().map(|x| &x[..]) {
+            Some("list") => {
+                let content = {
+                    let data = ctx.data.lock();
+                    let store = data.get::<TempRoleStore>().unwrap();
+                    let guild = guild.read();
+                    let now = Utc::now();
+
+                    let roles = store.in_guild(guild.id.0);
+
+                    if roles.is_empty() {
+                        "There are no active temporary roles".to_owned()
+                    } else {
+                        let mut s = "Active temporary roles:\n".to_owned();
+
+                        for temp_role in roles {
+                            let role_name = guild.roles.values()
+                                .find(|r| r.id == temp_role.role_id)
+                                .map_or_else(|| temp_role.role_id.to_string(), |r| r.name.clone());
+
+                            let _ = write!(s,
+                                           "- <@{}>: {} (expires in {})\n",
+                                           temp_role.user_id,
+                                           role_name,
+                                           duration::format(temp_role.expires_at - now));
+                        }
+
+                        s
+                    }
+                };
+
+                msg.channel_id.say(&content).map_err(Error::from)?;
+
+                return Ok(());
+            },
+            Some("log") => {
+                let content = {
+                    let data = ctx.data.lock();
+                    let log = data.get::<AuditLogStore>().unwrap();
+                    let guild_id = guild.read().id.0;
+
+                    match log.guilds.get(&guild_id) {
+                        Some(entries) if !entries.is_empty() => {
+                            let mut s = "Recent audit entries:\n".to_owned();
+
+                            for entry in entries.iter().rev().take(10) {
+                                let _ = write!(s,
+                                               "- `{}` {}\n",
+                                               &entry.at.to_rfc3339()[..19],
+                                               entry.description);
+                            }
+
+                            s
+                        },
+                        _ => "There are no audit entries".to_owned(),
+                    }
+                };
+
+                msg.channel_id.say(&content).map_err(Error::from)?;
+
+                return Ok(());
+            },
+            _ => {},
+        }
+
+        if words.len() < 3 {
+            return Err(Error::BadArgument(USAGE.to_owned()).into());
+        }
+
+        let length = match words.pop().as_ref().and_then(|x| duration::parse(x)) {
+            Some(length) => length,
+            None => return Err(Error::BadArgument(INVALID_DURATION.to_owned()).into()),
+        };
+        let member_arg = words.remove(0);
+        let role_arg = words.join(" ");
+
+        let (guild_id, role, invoker_position, is_owner) = {
+            let guild = guild.read();
+
+            (guild.id,
+             resolver::role(&guild, &role_arg),
+             permissions::top_role_position(&guild, msg.author.id),
+             guild.owner_id == msg.author.id)
+        };
+        let mut member = resolver::member(guild_id, &member_arg).or_error("member", resolver::describe_member)?;
+        let role = role.or_error("role", resolver::describe_role)?;
+
+        if role.position >= invoker_position && !is_owner {
+            let why = "You can only give out roles below your highest role";
+
+            return Err(Error::BadArgument(why.to_owned()).into());
+        }
+
+        let user_id = member.user.read().id;
+
+        let permanent = member.roles.contains(&role.id) && {
+            let data = ctx.data.lock();
+
+            !data.get::<TempRoleStore>().unwrap().contains(member.guild_id.0, user_id.0, role.id.0)
+        };
+
+        // Expiring it would take away a role that was given out for good.
+        if permanent {
+            let why = "That member already has that role permanently";
+
+            return Err(Error::BadArgument(why.to_owned()).into());
+        }
+
+        let now = Utc::now();
+        let expires_at = match now.checked_add_signed(length) {
+            Some(expires_at) => expires_at,
+            None => return Err(Error::BadArgument(INVALID_DURATION.to_owned()).into()),
+        };
+
+        actions::add_role(ctx, &mut member, role.id).map_err(Error::from)?;
+
+        {
+            let mut data = ctx.data.lock();
+
+            {
+                let store = data.get_mut::<TempRoleStore>().unwrap();
+                store.insert(TempRole {
+                    guild_id: member.guild_id.0,
+                    user_id: user_id.0,
+                    role_id: role.id.0,
+                    granted_by: msg.author.id.0,
+                    granted_at: now,
+                    expires_at: expires_at,
+                });
+                store.save();
+            }
+
+            let description = format!("Temporary role {} ({}) given to {} by {} until {}",
+                                      role.name,
+                                      role.id,
+                                      user_id,
+                                      msg.author.id,
+                                      &expires_at.to_rfc3339()[..19]);
+            data.get_mut::<AuditLogStore>().unwrap().record(member.guild_id.0, description);
+        }
+
+        let content = format!(":ok_hand: **{}** will be removed in {}", role.name, duration::format(length));
+        msg.channel_id.say(&content).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct ToggleCommand;
+
+impl Command for ToggleCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        const USAGE: &'static str = "Usage: `toggle <command|group> <on|off|reset> [channel]`";
+
+        let guild = match msg.guild() {
+            Some(guild) => guild,
+            None => return Err(Error::NotFound("server data".to_owned()).into()),
+        };
+        let guild = guild.read();
+
+        let mut words = args.multiple::<String>().unwrap_or_default();
+
+        if words.is_empty() {
+            let content = {
+                let data = ctx.data.lock();
+                let toggles = data.get::<CommandToggleStore>().unwrap();
+
+                let mut s = String::new();
+
+                if let Some(rules) = toggles.guilds.get(&guild.id.0) {
+                    s.push_str("Server:\n");
+                    write_rules(&mut s, rules);
+                }
+
+                for channel_id in guild.channels.keys() {
+                    if let Some(rules) = toggles.channels.get(&channel_id.0) {
+                        let _ = write!(s, "<#{}>:\n", channel_id);
+                        write_rules(&mut s, rules);
+                    }
+                }
+
+                if s.is_empty() {
+                    "All commands are enabled".to_owned()
+                } else {
+                    s
+                }
+            };
+
+            msg.channel_id.say(&content).map_err(Error::from)?;
+
+            return Ok(());
+        }
+
+        if words.len() < 2 {
+            return Err(Error::BadArgument(USAGE.to_owned()).into());
+        }
+
+        let target = words.remove(0).to_lowercase();
+        let value = match &words.remove(0)[..] {
+            "on" => Some(true),
+            "off" => Some(false),
+            "reset" => None,
+            _ => {
+                return Err(Error::BadArgument(USAGE.to_owned()).into());
+            },
+        };
+
+        let channel_id = if words.is_empty() {
+            None
+        } else {
+            let channel = resolver::channel(&guild, msg.author.id, &words.join(" "))
+                .or_error("channel", resolver::describe_channel)?;
+
+            Some(channel.id.0)
+        };
+
+        let group = commands::GROUPS.iter().find(|g| g.to_lowercase() == target);
+        let command = commands::canonical_name(&target);
+
+        let (name, is_group) = match group {
+            Some(group) => (group.to_string(), true),
+            None if commands::is_toggleable(command) => (command.to_owned(), false),
+            None => {
+                return Err(Error::NotFound("a command or group by that name that can be toggled".to_owned()).into());
+            },
+        };
+
+        {
+            let mut data = ctx.data.lock();
+            let toggles = data.get_mut::<CommandToggleStore>().unwrap();
+
+            {
+                let rules = match channel_id {
+                    Some(channel_id) => toggles.channels.entry(channel_id),
+                    None => toggles.guilds.entry(guild.id.0),
+                }.or_insert_with(Default::default);
+                let map = if is_group { &mut rules.groups } else { &mut rules.commands };
+
+                match value {
+                    Some(value) => map.insert(name, value),
+                    None => map.remove(&name),
+                };
+            }
+
+            toggles.save();
+        }
+
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+fn write_rules(s: &mut String, rules: &ToggleRules) {
+    let mut groups = rules.groups.iter().collect::<Vec<_>>();
+    groups.sort();
+
+    for (name, enabled) in groups {
+        let _ = write!(s, "- {} (group): {}\n", name, if *enabled { "on" } else { "off" });
+    }
+
+    let mut commands = rules.commands.iter().collect::<Vec<_>>();
+    commands.sort();
+
+    for (name, enabled) in commands {
+        let _ = write!(s, "- {}: {}\n", name, if *enabled { "on" } else { "off" });
+    }
+}
+
+/// Joins the arguments that haven't been consumed yet.
+fn rest(args: Args) -> String {
+    args.multiple::<String>().unwrap_or_default().join(" ")
+}
@@ -1,32 +1,383 @@
+use chrono::{Duration, Utc};
+use hyper::status::StatusCode;
 use psutil;
+use reqwest::Client;
+use serde_json;
 use serenity::client::CACHE;
+use serenity::client::rest::HttpError;
+use serenity::model::id::GuildId;
+use serenity::model::user::OnlineStatus;
+use serenity::Error as SerenityError;
+use std::cmp;
 use std::collections::BTreeMap;
 use std::fmt::Write as FmtWrite;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::process::{Command as ProcessCommand, Stdio};
-use std::env;
+use std::time::Instant;
+use ::actions;
+use ::commands;
+use ::misc::avatar::{self, PreviousAvatar};
+use ::misc::{
+    default_bucket,
+    duration,
+    Activity,
+    ActivityKind,
+    Bucket,
+    GroupBy,
+    Scope,
+    UsageFilter,
+    MAX_PER,
+};
 use ::prelude::*;
-use ::store::{CommandCounter, EventCounter};
+use ::resolver;
+use ::scheduler;
+use ::store::{
+    BootHistoryStore,
+    CommandCounter,
+    CommandTimings,
+    CooldownStore,
+    CpuSample,
+    DryRunConfig,
+    EventCounter,
+    GuildBlocklistStore,
+    PresenceStore,
+    UnknownEvents,
+};
+
+pub struct BlocklistCommand;
+
+impl Command for BlocklistCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let action = args.single::<String>().ok();
+        let guild_id = args.single::<u64>().ok();
+
+        let mut data = ctx.data.lock();
+        let blocklist = data.get_mut::<GuildBlocklistStore>().unwrap();
+
+        let content = match (action.as_ref().map(|x| &x[..]), guild_id) {
+            (Some("add"), Some(guild_id)) => {
+                blocklist.guilds.insert(guild_id);
+                blocklist.save();
+
+                ":ok_hand:".to_owned()
+            },
+            (Some("remove"), Some(guild_id)) => {
+                if blocklist.guilds.remove(&guild_id) {
+                    blocklist.save();
+
+                    ":ok_hand:".to_owned()
+                } else {
+                    return Err(Error::BadArgument("That guild is not blocklisted".to_owned()).into());
+                }
+            },
+            (None, _) => {
+                let mut ids = blocklist.guilds.iter().collect::<Vec<_>>();
+                ids.sort();
+
+                if ids.is_empty() {
+                    "No guilds are blocklisted".to_owned()
+                } else {
+                    let mut s = "Blocklisted guilds:\n".to_owned();
+
+                    for id in ids {
+                        let _ = write!(s, "- {}\n", id);
+                    }
+
+                    s
+                }
+            },
+            _ => return Err(Error::BadArgument("Usage: `blocklist [add|remove <guild id>]`".to_owned()).into()),
+        };
+
+        msg.channel_id.say(&content).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
 
 pub struct CommandsCommand;
 
 impl Command for CommandsCommand {
-    fn execute(&self, ctx: &mut Context, msg: &Message, _: Args) -> CommandResult {
-        let list = {
-            let mut s = "Commands used:\n".to_owned();
+    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        const USAGE: &'static str = "Usage: `commands [--guild <id|here>] [--user <user>] [--since <days|duration>] [--by command|guild|user|day] [--top <n>] [--export csv|json]`";
+
+        let mut filter = UsageFilter::default();
+        let mut by = GroupBy::Command;
+        let mut top = 20;
+        let mut export = None;
+
+        let words = args.multiple::<String>().unwrap_or_default();
+        let mut words = words.iter().map(|x| &x[..]);
+
+        while let Some(flag) = words.next() {
+            let value = words.next();
+
+            let valid = match (flag, value) {
+                ("--guild", Some("here")) => {
+                    filter.guild_id = msg.guild_id().map(|id| id.0);
+
+                    filter.guild_id.is_some()
+                },
+                ("--guild", Some(id)) => {
+                    filter.guild_id = id.parse().ok();
+
+                    filter.guild_id.is_some()
+                },
+                ("--user", Some(user)) => {
+                    filter.user_id = resolver::parse_user_id(user);
+
+                    filter.user_id.is_some()
+                },
+                ("--since", Some(since)) => {
+                    // Usage is counted per day, so a bare number is taken as
+                    // days rather than the usual minutes.
+                    let since = match since.parse::<u64>() {
+                        Ok(days) => format!("{}d", days),
+                        Err(_) => since.to_owned(),
+                    };
+                    filter.since = duration::parse(&since).map(|d| (Utc::now() - d).date().naive_utc());
+
+                    filter.since.is_some()
+                },
+                ("--by", Some("command")) => { by = GroupBy::Command; true },
+                ("--by", Some("day")) => { by = GroupBy::Day; true },
+                ("--by", Some("guild")) => { by = GroupBy::Guild; true },
+                ("--by", Some("user")) => { by = GroupBy::User; true },
+                ("--top", Some(n)) => match n.parse::<usize>() {
+                    Ok(n) if n > 0 => {
+                        top = cmp::min(n, 50);
+
+                        true
+                    },
+                    _ => false,
+                },
+                ("--export", Some(format)) if format == "csv" || format == "json" => {
+                    export = Some(format);
+
+                    true
+                },
+                _ => false,
+            };
+
+            if !valid {
+                return Err(Error::BadArgument(USAGE.to_owned()).into());
+            }
+        }
+
+        if let Some(format) = export {
+            let bytes = {
+                let data = ctx.data.lock();
+                let rows = data.get::<CommandCounter>().unwrap().filtered(&filter);
 
+                if format == "json" {
+                    serde_json::to_vec_pretty(&rows).map_err(|why| Error::Internal(format!("{:?}", why)))?
+                } else {
+                    let mut s = "day,command,guild_id,user_id,count\n".to_owned();
+
+                    for row in rows {
+                        let _ = write!(s,
+                                       "{},{},{},{},{}\n",
+                                       row.day,
+                                       row.command,
+                                       row.guild_id.map_or_else(String::new, |id| id.to_string()),
+                                       row.user_id,
+                                       row.count);
+                    }
+
+                    s.into_bytes()
+                }
+            };
+            let filename = format!("command_usage.{}", format);
+
+            msg.channel_id.send_files(vec![(&bytes[..], &filename[..])], |m| m).map_err(Error::from)?;
+
+            return Ok(());
+        }
+
+        let list = {
             let data = ctx.data.lock();
-            let counter = data.get::<CommandCounter>().unwrap();
+            let totals = data.get::<CommandCounter>().unwrap().summarize(&filter, by);
 
-            for (k, v) in counter.iter().collect::<BTreeMap<_, _>>() {
-                let _ = write!(s, "- {name}: {amount}\n", name=k, amount=v);
+            if totals.is_empty() {
+                "No commands matched".to_owned()
+            } else {
+                let total = totals.iter().map(|&(_, count)| count).sum::<u64>();
+                let mut s = format!("Commands used ({} total):\n", total);
+
+                for &(ref key, count) in totals.iter().take(top) {
+                    let name = match by {
+                        GroupBy::User => format!("<@{}>", key),
+                        _ => key.clone(),
+                    };
+
+                    let _ = write!(s, "- {name}: {amount}\n", name=name, amount=count);
+                }
+
+                if totals.len() > top {
+                    let _ = write!(s, "...and {} more\n", totals.len() - top);
+                }
+
+                s
             }
+        };
 
-            s
+        msg.channel_id.say(&list).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct CooldownCommand;
+
+impl Command for CooldownCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        const USAGE: &'static str = "Usage: `cooldown [<command> <user|channel|guild|global> <uses> <duration>]`, `cooldown <command> off` or `cooldown <command> reset`. Durations can be up to a day.";
+
+        let words = args.multiple::<String>().unwrap_or_default();
+
+        if words.is_empty() {
+            let list = {
+                let data = ctx.data.lock();
+                let cooldowns = data.get::<CooldownStore>().unwrap();
+
+                let mut names = commands::TOGGLEABLE.iter().map(|&(name, _)| name.to_owned()).collect::<Vec<_>>();
+                names.push("eval".to_owned());
+                names.extend(cooldowns.buckets.keys().cloned());
+                names.sort();
+                names.dedup();
+
+                let mut s = "Cooldowns:\n".to_owned();
+
+                for name in names {
+                    let bucket = match cooldowns.bucket(&name) {
+                        Some(bucket) => bucket,
+                        None => continue,
+                    };
+
+                    let _ = write!(s,
+                                   "- {}: {} per {} per {}{}{}\n",
+                                   name,
+                                   bucket.uses,
+                                   duration::format(Duration::seconds(bucket.per as i64)),
+                                   bucket.scope.name(),
+                                   if bucket.owner_bypass { "" } else { ", including owners" },
+                                   if cooldowns.buckets.contains_key(&name) { "" } else { " (default)" });
+                }
+
+                s
+            };
+
+            msg.channel_id.say(&list).map_err(Error::from)?;
+
+            return Ok(());
+        }
+
+        let name = commands::canonical_name(&words[0].to_lowercase()).to_owned();
+
+        let bucket = match (words.get(1).map(|x| &x[..]), words.len()) {
+            (Some("off"), 2) => Some(None),
+            (Some("reset"), 2) => None,
+            (Some(scope), 4) => {
+                let scope = Scope::parse(scope);
+                let uses = words[2].parse::<usize>().ok();
+                let per = duration::parse(&words[3]);
+
+                match (scope, uses, per) {
+                    (Some(scope), Some(uses), Some(per))
+                        if uses > 0 && per.num_seconds() > 0 && per.num_seconds() as u64 <= MAX_PER => {
+                        let owner_bypass = default_bucket(&name).map_or(true, |b| b.owner_bypass);
+
+                        Some(Some(Bucket {
+                            scope: scope,
+                            uses: uses,
+                            per: per.num_seconds() as u64,
+                            owner_bypass: owner_bypass,
+                        }))
+                    },
+                    _ => {
+                        return Err(Error::BadArgument(USAGE.to_owned()).into());
+                    },
+                }
+            },
+            _ => {
+                return Err(Error::BadArgument(USAGE.to_owned()).into());
+            },
+        };
+
+        {
+            let mut data = ctx.data.lock();
+            let cooldowns = data.get_mut::<CooldownStore>().unwrap();
+
+            match bucket {
+                Some(bucket) => cooldowns.buckets.insert(name, bucket),
+                None => cooldowns.buckets.remove(&name),
+            };
+
+            cooldowns.save();
+        }
+
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct DryRunCommand;
+
+impl Command for DryRunCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let enable = match args.single::<String>().as_ref().map(|x| &x[..]) {
+            Ok("on") => true,
+            Ok("off") => false,
+            Ok(_) => {
+                return Err(Error::BadArgument("Usage: `dryrun [on|off] [guild id]`".to_owned()).into());
+            },
+            Err(_) => {
+                let status = {
+                    let data = ctx.data.lock();
+                    let config = data.get::<DryRunConfig>().unwrap();
+
+                    let mut guilds = config.guilds.iter().collect::<Vec<_>>();
+                    guilds.sort();
+
+                    format!("Global dry run: {}\nGuilds: {}",
+                            if config.global { "on" } else { "off" },
+                            if guilds.is_empty() {
+                                "none".to_owned()
+                            } else {
+                                guilds.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+                            })
+                };
+
+                msg.channel_id.say(&status).map_err(Error::from)?;
+
+                return Ok(());
+            },
+        };
+
+        let guild_id = match args.single::<u64>() {
+            Ok(guild_id) => Some(guild_id),
+            Err(_) if args.is_empty() => None,
+            Err(_) => {
+                return Err(Error::BadArgument("Invalid guild ID".to_owned()).into());
+            },
         };
 
-        let _ = msg.channel_id.say(&list);
+        {
+            let mut data = ctx.data.lock();
+            let config = data.get_mut::<DryRunConfig>().unwrap();
+
+            match guild_id {
+                Some(guild_id) if enable => drop(config.guilds.insert(guild_id)),
+                Some(guild_id) => drop(config.guilds.remove(&guild_id)),
+                None => config.global = enable,
+            }
+
+            config.save();
+        }
+
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
 
         Ok(())
     }
@@ -42,9 +393,7 @@ impl Command for EvalCommand {
             let mut runnable = match File::open("./runnable.rs") {
                 Ok(runnable) => runnable,
                 Err(_) => {
-                    let _ = msg.channel_id.say("Err opening runnable");
-
-                    return Ok(());
+                    return Err(Error::Internal("Err opening runnable".to_owned()).into());
                 },
             };
 
@@ -89,32 +438,28 @@ impl Command for EvalCommand {
                         let mut s = String::from_utf8_lossy(&output.stderr).into_owned();
                         s.truncate(500);
 
-                        let _ = msg.channel_id.say(&format!("Error running rustc:
+                        return Err(Error::BadArgument(format!("Error running rustc:
     ```
     {}
-    ```", s));
-
-                        return Ok(());
+    ```", s)).into());
                     }
 
                     info!("end out");
                 },
                 Err(why) => {
-                    let _ = msg.channel_id.say(&format!("Error running rustc: {:?}", why));
-
-                    return Ok(());
+                    return Err(Error::Internal(format!("Error running rustc: {:?}", why)).into());
                 },
             };
         }
 
         info!("c");
 
-        match ProcessCommand::new(&path).stdout(Stdio::piped()).stderr(Stdio::piped()).output() {
+        let res = match ProcessCommand::new(&path).stdout(Stdio::piped()).stderr(Stdio::piped()).output() {
             Ok(output) => {
                 let mut out = String::from_utf8_lossy(&output.stdout).into_owned();
                 out.truncate(2000 - query.len() - 100);
 
-                let _ = msg.channel_id.say(&format!("
+                msg.channel_id.say(&format!("
     **Exit status**: {}
     **In**:
     ```rs
@@ -123,16 +468,16 @@ impl Command for EvalCommand {
     **Out**:
     ```rs
     {}
-    ```", output.status.code().unwrap_or(1), query, out));
-            },
-            Err(why) => {
-                let _ = msg.channel_id.say(&format!("Err running program: {:?}", why));
+    ```", output.status.code().unwrap_or(1), query, out)).map(|_| ()).map_err(Error::from)
             },
-        }
+            Err(why) => Err(Error::Internal(format!("Err running program: {:?}", why))),
+        };
 
         let _ = fs::remove_file(id);
         let _ = fs::remove_file(path);
 
+        res?;
+
         Ok(())
     }
 }
@@ -154,7 +499,125 @@ impl Command for EventsCommand {
             s
         };
 
-        let _ = msg.channel_id.say(&list);
+        msg.channel_id.say(&list).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct GuildsCommand;
+
+impl Command for GuildsCommand {
+    fn execute(&self, _: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        const PER_PAGE: usize = 20;
+
+        let page = args.single::<usize>().unwrap_or(1).max(1);
+
+        let mut guilds = CACHE.read()
+            .guilds
+            .values()
+            .map(|guild| {
+                let guild = guild.read();
+
+                (guild.name.clone(), guild.id, guild.member_count)
+            })
+            .collect::<Vec<_>>();
+        guilds.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
+
+        let pages = (guilds.len() + PER_PAGE - 1) / PER_PAGE;
+
+        if page > pages.max(1) {
+            return Err(Error::BadArgument(format!("There are only {} pages", pages)).into());
+        }
+
+        let mut s = format!("Guilds ({} total), page {}/{}:\n", guilds.len(), page, pages.max(1));
+
+        for &(ref name, id, members) in guilds.iter().skip((page - 1) * PER_PAGE).take(PER_PAGE) {
+            let _ = write!(s, "- {} ({}): {} members\n", name, id, members);
+        }
+
+        msg.channel_id.say(&s).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct LeaveCommand;
+
+impl Command for LeaveCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let guild_id = match args.single::<u64>() {
+            Ok(guild_id) => GuildId(guild_id),
+            Err(_) => {
+                return Err(Error::BadArgument("Usage: `leave <guild id> [block]`".to_owned()).into());
+            },
+        };
+        let block = args.single::<String>().map(|x| x == "block").unwrap_or(false);
+
+        if block {
+            let mut data = ctx.data.lock();
+            let blocklist = data.get_mut::<GuildBlocklistStore>().unwrap();
+            blocklist.guilds.insert(guild_id.0);
+            blocklist.save();
+        }
+
+        actions::leave_guild(ctx, guild_id).map_err(Error::from)?;
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct SetAvatarCommand;
+
+impl Command for SetAvatarCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        const USAGE: &'static str = "Usage: `set avatar <url>`, `set avatar` with an attached image or `set avatar revert`";
+
+        let arg = args.single::<String>().ok();
+
+        let data = if arg.as_ref().map_or(false, |x| x == "revert") {
+            match PreviousAvatar::load().data {
+                Some(data) => data,
+                None => {
+                    return Err(Error::NotFound("a previous avatar to revert to".to_owned()).into());
+                },
+            }
+        } else {
+            let bytes = match (msg.attachments.first(), arg) {
+                (Some(attachment), _) if attachment.size as usize > avatar::MAX_SIZE => {
+                    Err(Error::BadArgument("The image is too large".to_owned()))
+                },
+                (Some(attachment), _) => attachment.download().map_err(|why| Error::upstream("Discord", why)),
+                (None, Some(url)) => download_image(&url),
+                (None, None) => Err(Error::BadArgument(USAGE.to_owned())),
+            }?;
+
+            match avatar::data_uri(&bytes) {
+                Some(data) => data,
+                None => {
+                    return Err(Error::BadArgument("Avatars must be PNG, JPEG or GIF images".to_owned()).into());
+                },
+            }
+        };
+
+        // The current avatar is kept before changing it, so that even a
+        // revert can be reverted.
+        let previous = current_avatar();
+
+        if let Err(why) = actions::edit_profile(ctx, |p| p.avatar(Some(&data))) {
+            return Err(avatar_error(why).into());
+        }
+
+        // In dry-run mode the avatar wasn't actually changed, so the saved
+        // one is still the one to revert to.
+        if previous.is_some() && !actions::is_dry(&ctx.data, None) {
+            PreviousAvatar {
+                data: previous,
+            }.save();
+        }
+
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
 
         Ok(())
     }
@@ -165,22 +628,140 @@ pub struct SetNameCommand;
 impl Command for SetNameCommand {
     fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
         if args.is_empty() {
-            let _ = msg.reply("No name given");
-
-            return Ok(());
+            return Err(Error::BadArgument("No name given".to_owned()).into());
         }
 
         let name = args.full();
 
-        let _ = match ctx.edit_profile(|p| p.username(&name)) {
-            Ok(_) => msg.reply(":ok_hand:"),
-            Err(why) => {
-                warn!("Err setting name: {:?}", why);
+        actions::edit_profile(ctx, |p| p.username(&name)).map_err(Error::from)?;
+        msg.reply(":ok_hand:").map_err(Error::from)?;
+
+        Ok(())
+    }
+}
 
-                msg.reply(":x: Error editing name")
+pub struct SetActivityCommand;
+
+impl Command for SetActivityCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        const USAGE: &'static str = "Usage: `set activity <playing|listening> <text>`, `set activity streaming <url> <text>` or `set activity none`";
+
+        let words = args.multiple::<String>().unwrap_or_default();
+
+        let activities = match words.first().map(|x| &x[..]) {
+            Some("none") => vec![],
+            _ => match parse_activity(&words) {
+                Some(activity) => vec![activity],
+                None => {
+                    return Err(Error::BadArgument(USAGE.to_owned()).into());
+                },
             },
         };
 
+        {
+            let mut data = ctx.data.lock();
+            let presence = data.get_mut::<PresenceStore>().unwrap();
+            presence.activities = activities;
+            presence.reset();
+            presence.save();
+        }
+
+        scheduler::update_presence(&ctx.data, None);
+
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct SetRotationCommand;
+
+impl Command for SetRotationCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+        let mut words = args.multiple::<String>().unwrap_or_default();
+
+        let subcommand = if words.is_empty() {
+            String::new()
+        } else {
+            words.remove(0)
+        };
+
+        if subcommand.is_empty() {
+            let list = {
+                let data = ctx.data.lock();
+                let presence = data.get::<PresenceStore>().unwrap();
+
+                let mut s = format!("Status: {:?}\nRotating every {}\n",
+                                    presence.status,
+                                    duration::format(Duration::seconds(presence.interval as i64)));
+
+                if presence.activities.is_empty() {
+                    s.push_str("No activities");
+                }
+
+                for (i, activity) in presence.activities.iter().enumerate() {
+                    let _ = write!(s, "{}. {} {}", i + 1, activity.kind.name(), activity.text);
+
+                    if let Some(ref url) = activity.url {
+                        let _ = write!(s, " (<{}>)", url);
+                    }
+
+                    s.push('\n');
+                }
+
+                s
+            };
+
+            msg.channel_id.say(&list).map_err(Error::from)?;
+
+            return Ok(());
+        }
+
+        {
+            let mut data = ctx.data.lock();
+            let presence = data.get_mut::<PresenceStore>().unwrap();
+
+            let result = match &subcommand[..] {
+                "add" => match parse_activity(&words) {
+                    Some(activity) => {
+                        presence.activities.push(activity);
+
+                        Ok(())
+                    },
+                    None => Err("Usage: `set rotation add <playing|listening> <text>` or `set rotation add streaming <url> <text>`"),
+                },
+                "remove" => match words.first().and_then(|x| x.parse::<usize>().ok()) {
+                    Some(n) if n > 0 && n <= presence.activities.len() => {
+                        presence.activities.remove(n - 1);
+
+                        Ok(())
+                    },
+                    _ => Err("Usage: `set rotation remove <number>`"),
+                },
+                "interval" => match words.first().and_then(|x| duration::parse(x)) {
+                    Some(interval) if interval.num_seconds() >= 60 => {
+                        presence.interval = interval.num_seconds() as u64;
+
+                        Ok(())
+                    },
+                    Some(_) => Err("The interval must be at least a minute"),
+                    None => Err("Usage: `set rotation interval <duration>`"),
+                },
+                _ => Err("Usage: `set rotation [add|remove|interval]`"),
+            };
+
+            if let Err(why) = result {
+                return Err(Error::BadArgument(why.to_owned()).into());
+            }
+
+            presence.reset();
+            presence.save();
+        }
+
+        scheduler::update_presence(&ctx.data, None);
+
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
+
         Ok(())
     }
 }
@@ -188,21 +769,27 @@ impl Command for SetNameCommand {
 pub struct SetStatusCommand;
 
 impl Command for SetStatusCommand {
-    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
-        let author_id = match env::var("AUTHOR_ID").map(|x| x.parse::<u64>()) {
-            Ok(Ok(author_id)) => author_id,
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let status = match args.single::<String>().as_ref().map(|x| &x[..]) {
+            Ok("online") => OnlineStatus::Online,
+            Ok("idle") => OnlineStatus::Idle,
+            Ok("dnd") => OnlineStatus::DoNotDisturb,
+            Ok("invisible") => OnlineStatus::Invisible,
             _ => {
-                error!("Valid AUTHOR_ID env var not set");
-
-                return Ok(());
+                return Err(Error::BadArgument("Usage: `set status <online|idle|dnd|invisible>`".to_owned()).into());
             },
         };
 
-        if msg.author.id != author_id {
-            return Ok(());
+        {
+            let mut data = ctx.data.lock();
+            let presence = data.get_mut::<PresenceStore>().unwrap();
+            presence.status = status;
+            presence.save();
         }
 
-        ctx.set_game_name(args.full());
+        scheduler::update_presence(&ctx.data, None);
+
+        msg.channel_id.say(":ok_hand:").map_err(Error::from)?;
 
         Ok(())
     }
@@ -211,35 +798,24 @@ impl Command for SetStatusCommand {
 pub struct StatsCommand;
 
 impl Command for StatsCommand {
-    fn execute(&self, _: &mut Context, msg: &Message, _: Args) -> CommandResult {
-        let processes = match psutil::process::all() {
-            Ok(processes) => processes,
-            Err(why) => {
-                println!("Err getting processes: {:?}", why);
-
-                let _ = msg.channel_id.say("Error getting stats");
-
-                return Ok(());
-            },
-        };
+    fn execute(&self, ctx: &mut Context, msg: &Message, _: Args) -> CommandResult {
+        let processes = psutil::process::all()
+            .map_err(|why| Error::Internal(format!("Err getting processes: {:?}", why)))?;
 
         let process = match processes.iter().find(|p| p.pid == psutil::getpid()) {
             Some(process) => process,
-            None => {
-                let _ = msg.channel_id.say("Error getting stats");
-
-                return Ok(());
-            },
+            None => return Err(Error::Internal("Own process not found".to_owned()).into()),
         };
 
-        let memory = match process.memory() {
-            Ok(memory) => memory,
-            Err(why) => {
-                println!("Err getting process memory: {:?}", why);
+        let memory = process.memory()
+            .map_err(|why| Error::Internal(format!("Err getting process memory: {:?}", why)))?;
 
-                let _ = msg.channel_id.say("Error getting stats");
+        let fds = match process.open_fds() {
+            Ok(fds) => fds.len().to_string(),
+            Err(why) => {
+                warn!("Err getting open fds: {:?}", why);
 
-                return Ok(());
+                "N/A".to_owned()
             },
         };
 
@@ -248,15 +824,278 @@ impl Command for StatsCommand {
         let mem_total = memory.size / B_TO_MB;
         let mem_rss = memory.resident / B_TO_MB;
         let memory = format!("{}MB/{}MB (RSS/Total)", mem_rss, mem_total);
-        let guilds = CACHE.read().guilds.len();
 
-        let _ = msg.channel_id.send_message(|m|
+        // CPU usage is the CPU time used since the last sample, which is taken
+        // at startup and then by each call, over the wall time since then.
+        let cpu_time = process.utime + process.stime;
+        let (cpu, uptime) = {
+            let mut data = ctx.data.lock();
+
+            let cpu = {
+                let sample = data.get_mut::<CpuSample>().unwrap();
+                let elapsed = sample.0.elapsed();
+                let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+                let usage = (cpu_time - sample.1) / elapsed * 100.0;
+                *sample = (Instant::now(), cpu_time);
+
+                format!("{:.2}% over {}", usage, duration::format(Duration::seconds(elapsed as i64)))
+            };
+
+            let uptime = data.get::<BootHistoryStore>()
+                .unwrap()
+                .sessions
+                .last()
+                .map_or_else(|| "N/A".to_owned(), |s| duration::format(Utc::now() - s.start));
+
+            (cpu, uptime)
+        };
+
+        let (guilds, shards, users, channels, members, presences, messages) = {
+            let cache = CACHE.read();
+            let guilds = cache.guilds.values().map(|g| g.read()).collect::<Vec<_>>();
+
+            (cache.guilds.len(),
+             cache.shard_count,
+             cache.users.len(),
+             cache.channels.len() + cache.private_channels.len() + cache.groups.len(),
+             guilds.iter().map(|g| g.members.len()).sum::<usize>(),
+             cache.presences.len() + guilds.iter().map(|g| g.presences.len()).sum::<usize>(),
+             cache.messages.values().map(|m| m.len()).sum::<usize>())
+        };
+
+        let version = format!("{} ({}, {})",
+                              env!("CARGO_PKG_VERSION"),
+                              env!("NANOBOT_GIT_COMMIT"),
+                              env!("NANOBOT_BUILD_PROFILE"));
+
+        msg.channel_id.send_message(|m|
             m.embed(|e| e
                 .title("Stats")
-                .field("Version", "0.1.0", true)
+                .field("Version", &version, true)
+                .field("Uptime", &uptime, true)
+                .field("Shards", &shards.to_string(), true)
                 .field("Guilds", &guilds.to_string(), true)
-                .field("Memory Used", &memory, true)));
+                .field("Channels", &channels.to_string(), true)
+                .field("Users", &users.to_string(), true)
+                .field("Members", &members.to_string(), true)
+                .field("Presences", &presences.to_string(), true)
+                .field("Messages", &messages.to_string(), true)
+                .field("Memory Used", &memory, true)
+                .field("CPU", &cpu, true)
+                .field("Threads", &process.num_threads.to_string(), true)
+                .field("Open Files", &fds, true)
+                .field("Rust", env!("NANOBOT_RUSTC_VERSION"), false)
+                .field("Serenity", env!("NANOBOT_SERENITY_VERSION"), false))).map_err(Error::from)?;
+
+        Ok(())
+    }
+}
+
+pub struct TimingsCommand;
+
+impl Command for TimingsCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, _: Args) -> CommandResult {
+        const HEADER: &'static str = "```\nCommand          | Count  | Mean   | p50    | p95    | Max\n";
+        const FOOTER: &'static str = "```";
+
+        // The table is split over as many messages as it takes to keep each
+        // under Discord's limit, repeating the header in each.
+        let tables = {
+            let data = ctx.data.lock();
+            let timings = data.get::<CommandTimings>().unwrap();
+
+            if timings.commands.is_empty() {
+                vec!["No commands have been timed yet".to_owned()]
+            } else {
+                let mut tables = vec![];
+                let mut s = HEADER.to_owned();
+
+                for (name, stats) in timings.commands.iter().collect::<BTreeMap<_, _>>() {
+                    let row = format!("{:<16} | {:<6} | {:<6} | {:<6} | {:<6} | {}\n",
+                                      name,
+                                      stats.count,
+                                      format!("{}ms", stats.mean()),
+                                      format!("{}ms", stats.percentile(0.5)),
+                                      format!("{}ms", stats.percentile(0.95)),
+                                      format!("{}ms", stats.max));
+
+                    if s.len() > HEADER.len() && s.len() + row.len() + FOOTER.len() > 2000 {
+                        s.push_str(FOOTER);
+                        tables.push(s);
+                        s = HEADER.to_owned();
+                    }
+
+                    s.push_str(&row);
+                }
+
+                s.push_str(FOOTER);
+                tables.push(s);
+
+                tables
+            }
+        };
+
+        for table in tables {
+            msg.channel_id.say(&table).map_err(Error::from)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct UnknownCommand;
+
+impl Command for UnknownCommand {
+    fn execute(&self, ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+        let name = match args.single::<String>() {
+            Ok(name) => name,
+            Err(_) => {
+                let list = {
+                    let data = ctx.data.lock();
+                    let store = data.get::<UnknownEvents>().unwrap();
+
+                    if store.events.is_empty() {
+                        "No unknown events received".to_owned()
+                    } else {
+                        let mut s = "Unknown events received:\n".to_owned();
+
+                        for (name, event) in &store.events {
+                            let _ = write!(s,
+                                           "- {}: {} (first {}, last {}, {} samples)\n",
+                                           name,
+                                           event.count,
+                                           &event.first_seen.to_rfc3339()[..19],
+                                           &event.last_seen.to_rfc3339()[..19],
+                                           event.samples.len());
+                        }
+
+                        s
+                    }
+                };
+
+                msg.channel_id.say(&list).map_err(Error::from)?;
+
+                return Ok(());
+            },
+        };
+
+        // Samples are 1-indexed for the user, defaulting to the most recent.
+        let index = args.single::<usize>().ok();
+
+        let bytes = {
+            let data = ctx.data.lock();
+            let store = data.get::<UnknownEvents>().unwrap();
+
+            let event = match store.events.get(&name) {
+                Some(event) => event,
+                None => {
+                    return Err(Error::NotFound("an unknown event by that name".to_owned()).into());
+                },
+            };
+
+            let sample = match index {
+                Some(0) => None,
+                Some(index) => event.samples.get(index - 1),
+                None => event.samples.back(),
+            };
+
+            match sample.map(serde_json::to_vec_pretty) {
+                Some(Ok(bytes)) => bytes,
+                Some(Err(why)) => {
+                    let why = format!("Err serializing sample of '{}': {:?}", name, why);
+
+                    return Err(Error::Internal(why).into());
+                },
+                None => {
+                    let why = format!("No such sample; {} available", event.samples.len());
+
+                    return Err(Error::BadArgument(why).into());
+                },
+            }
+        };
+
+        let filename = format!("{}.json", name);
+
+        msg.channel_id.send_files(vec![(&bytes[..], &filename[..])], |m| m).map_err(Error::from)?;
 
         Ok(())
     }
 }
+
+/// Parses `<playing|listening> <text>` or `streaming <url> <text>`.
+fn parse_activity(words: &[String]) -> Option<Activity> {
+    let kind = words.first().and_then(|x| ActivityKind::parse(x))?;
+
+    let (url, text) = match kind {
+        ActivityKind::Streaming => (Some(words.get(1)?.clone()), words.get(2..)?.join(" ")),
+        _ => (None, words[1..].join(" ")),
+    };
+
+    if text.is_empty() {
+        return None;
+    }
+
+    Some(Activity {
+        kind: kind,
+        text: text,
+        url: url,
+    })
+}
+
+/// Retrieves the bot's current avatar as a data URI.
+fn current_avatar() -> Option<String> {
+    let url = {
+        let cache = CACHE.read();
+        let hash = cache.user.avatar.clone()?;
+
+        format!("https://cdn.discordapp.com/avatars/{}/{}.png?size=1024", cache.user.id, hash)
+    };
+
+    match download_image(&url) {
+        Ok(bytes) => avatar::data_uri(&bytes),
+        Err(why) => {
+            warn!("Err downloading current avatar: {:?}", why);
+
+            None
+        },
+    }
+}
+
+/// Describes why changing the avatar failed, calling out Discord's limit on
+/// how often it can be changed.
+fn avatar_error(why: SerenityError) -> Error {
+    match why {
+        SerenityError::Http(HttpError::UnsuccessfulRequest(mut res)) => {
+            let mut body = String::new();
+            let _ = res.read_to_string(&mut body);
+
+            if res.status == StatusCode::TooManyRequests || body.contains("too fast") {
+                Error::BadArgument("Discord is rate limiting avatar changes; try again later".to_owned())
+            } else {
+                Error::BadArgument(format!("Discord rejected the avatar ({}): {}", res.status, body))
+            }
+        },
+        why => Error::from(why),
+    }
+}
+
+/// Downloads an image, refusing ones larger than an avatar may be.
+fn download_image(url: &str) -> Result<Vec<u8>, Error> {
+    let res = Client::new().get(url).send().map_err(|why| Error::upstream("The image host", why))?;
+
+    if !res.status().is_success() {
+        return Err(Error::BadArgument(format!("Error downloading the image: {}", res.status())));
+    }
+
+    let mut bytes = vec![];
+
+    if let Err(why) = res.take(avatar::MAX_SIZE as u64 + 1).read_to_end(&mut bytes) {
+        return Err(Error::upstream("The image host", why));
+    }
+
+    if bytes.len() > avatar::MAX_SIZE {
+        return Err(Error::BadArgument("The image is too large".to_owned()));
+    }
+
+    Ok(bytes)
+}
@@ -16,9 +16,7 @@ impl Command for ChooseCommand {
         choices.dedup();
 
         if choices.len() < 2 {
-            let _ = msg.channel_id.say("Must have at least 2 choices");
-
-            return Ok(());
+            return Err(Error::BadArgument("Must have at least 2 choices".to_owned()).into());
         }
 
         let _ = match rand::thread_rng().choose(&choices) {
@@ -36,11 +34,11 @@ impl Command for CoinflipCommand {
     fn execute(&self, _: &mut Context, msg: &Message, _: Args) -> CommandResult {
         let num = rand::thread_rng().gen::<u8>();
 
-        let _ = msg.channel_id.say(match num {
+        msg.channel_id.say(match num {
             0 ... 126 => "Heads",
             128 ... 255 => "Tails",
             _ => "On its side",
-        });
+        }).map_err(Error::from)?;
 
         Ok(())
     }
@@ -84,9 +82,7 @@ pub struct RollCommand;
 impl Command for RollCommand {
     fn execute(&self, _: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
         if !args.is_empty() && args.len() != 2 {
-            let _ = msg.channel_id.say("Either 0 or 2 numbers must be given");
-
-            return Ok(());
+            return Err(Error::BadArgument("Either 0 or 2 numbers must be given".to_owned()).into());
         }
 
         let nums = {
@@ -98,17 +94,13 @@ impl Command for RollCommand {
                 let arg1 = match arg1.parse::<isize>() {
                     Ok(arg1) => arg1,
                     Err(_) => {
-                        let _ = msg.channel_id.say(&format!("{} is not an integer", arg1));
-
-                        return Ok(());
+                        return Err(Error::BadArgument(format!("{} is not an integer", arg1)).into());
                     },
                 };
                 let arg2 = match arg2.parse::<isize>() {
                     Ok(arg2) => arg2,
                     Err(_) => {
-                        let _ = msg.channel_id.say(&format!("{} is not an integer", arg2));
-
-                        return Ok(());
+                        return Err(Error::BadArgument(format!("{} is not an integer", arg2)).into());
                     },
                 };
 
@@ -120,14 +112,12 @@ impl Command for RollCommand {
         };
 
         if nums[0] == nums[1] {
-            let _ = msg.channel_id.say("The given integers can not be equal");
-
-            return Ok(());
+            return Err(Error::BadArgument("The given integers can not be equal".to_owned()).into());
         }
 
         let number = rand::thread_rng().gen_range(nums[0], nums[1]);
 
-        let _ = msg.channel_id.say(&number.to_string());
+        msg.channel_id.say(&number.to_string()).map_err(Error::from)?;
 
         Ok(())
     }
@@ -143,7 +133,7 @@ impl Command for RouletteCommand {
             r"\*click\*".to_owned()
         };
 
-        let _ = msg.channel_id.say(&result);
+        msg.channel_id.say(&result).map_err(Error::from)?;
 
         Ok(())
     }
@@ -0,0 +1,183 @@
+//! The errors that commands return, and how they're reported.
+//!
+//! serenity's `CommandError` only carries a string, so errors are serialized
+//! into it as JSON and parsed back out in the `after` hook. Errors that were
+//! converted from anything else - e.g. with `?` on a foreign error - are
+//! treated as internal.
+
+use hyper::status::StatusCode;
+use serde_json;
+use serenity::client::Context;
+use serenity::client::rest::HttpError;
+use serenity::framework::standard::{Args, Command, CommandError, CommandOptions};
+use serenity::model::channel::Message;
+use serenity::Error as SerenityError;
+use std::any::Any;
+use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use ::actions;
+use ::store::ErrorReports;
+
+/// The least time between reports of internal errors to the owner, in
+/// seconds.
+const REPORT_INTERVAL: u64 = 60;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum Error {
+    /// What the user asked for doesn't exist; holds what couldn't be found.
+    NotFound(String),
+    /// The arguments couldn't be used; holds what was wrong with them.
+    BadArgument(String),
+    /// A service the command relies on failed.
+    Upstream {
+        service: String,
+        detail: String,
+    },
+    /// The bot isn't allowed to do what the command needs.
+    Permission(String),
+    /// Anything else, which is a bug or an outage on our end.
+    Internal(String),
+}
+
+impl Error {
+    pub fn upstream<T: Into<String>, U: Debug>(service: T, why: U) -> Self {
+        Error::Upstream {
+            service: service.into(),
+            detail: format!("{:?}", why),
+        }
+    }
+
+    /// The message shown to the user who invoked the command.
+    pub fn user_message(&self) -> String {
+        match *self {
+            Error::NotFound(ref what) => format!(":mag: Could not find {}", what),
+            Error::BadArgument(ref why) => format!(":x: {}", why),
+            Error::Upstream { ref service, .. } => {
+                format!(":warning: {} isn't responding right now; try again later", service)
+            },
+            Error::Permission(ref what) => format!(":no_entry: I'm not allowed to {}", what),
+            Error::Internal(_) => ":x: Something went wrong; the owner has been told".to_owned(),
+        }
+    }
+}
+
+impl From<Error> for CommandError {
+    fn from(error: Error) -> Self {
+        match serde_json::to_string(&error) {
+            Ok(json) => CommandError(json),
+            Err(_) => CommandError(format!("{:?}", error)),
+        }
+    }
+}
+
+impl From<CommandError> for Error {
+    fn from(error: CommandError) -> Self {
+        serde_json::from_str(&error.0).unwrap_or_else(|_| Error::Internal(error.0))
+    }
+}
+
+impl From<SerenityError> for Error {
+    fn from(error: SerenityError) -> Self {
+        match error {
+            SerenityError::Http(HttpError::UnsuccessfulRequest(ref res)) if res.status == StatusCode::Forbidden => {
+                Error::Permission("do that here".to_owned())
+            },
+            error => Error::Internal(format!("{:?}", error)),
+        }
+    }
+}
+
+/// Wraps a command so that a panic in it is returned as an internal error,
+/// instead of silently taking down the thread it ran on.
+pub struct Guarded<C>(pub C);
+
+impl<C: Command> Command for Guarded<C> {
+    fn execute(&self, ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.0.execute(ctx, msg, args))) {
+            Ok(res) => res,
+            Err(payload) => Err(Error::Internal(format!("Panicked: {}", panic_message(&payload))).into()),
+        }
+    }
+
+    fn options(&self) -> Arc<CommandOptions> {
+        self.0.options()
+    }
+}
+
+fn panic_message(payload: &Box<Any + Send>) -> String {
+    payload.downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown cause".to_owned())
+}
+
+/// When an internal error was last reported to the owner, and how many have
+/// happened since without being reported.
+#[derive(Debug, Default)]
+pub struct Reports {
+    last: Option<Instant>,
+    suppressed: usize,
+}
+
+/// Tells the user that their command failed and logs why, reporting internal
+/// errors to the owner.
+pub fn handle(ctx: &Context, msg: &Message, command: &str, error: Error) {
+    match error {
+        Error::Internal(ref why) => error!("Err in '{}' for {} in {}: {}", command, msg.author.id, msg.channel_id, why),
+        Error::Upstream { ref service, ref detail } => {
+            warn!("Err from {} in '{}' for {} in {}: {}", service, command, msg.author.id, msg.channel_id, detail)
+        },
+        ref error => debug!("'{}' failed for {} in {}: {:?}", command, msg.author.id, msg.channel_id, error),
+    }
+
+    let _ = msg.channel_id.say(&error.user_message());
+
+    if let Error::Internal(why) = error {
+        report(ctx, msg, command, &why);
+    }
+}
+
+/// DMs the owner about an internal error, at most once per
+/// `REPORT_INTERVAL`.
+fn report(ctx: &Context, msg: &Message, command: &str, why: &str) {
+    let suppressed = {
+        let mut data = ctx.data.lock();
+        let reports = data.get_mut::<ErrorReports>().unwrap();
+
+        if reports.last.map_or(false, |at| at.elapsed() < Duration::from_secs(REPORT_INTERVAL)) {
+            reports.suppressed += 1;
+
+            return;
+        }
+
+        reports.last = Some(Instant::now());
+
+        ::std::mem::replace(&mut reports.suppressed, 0)
+    };
+
+    let mut content = format!("Internal error in `{}`, used by {} ({}) in <#{}>:\n```\n{}\n```",
+                              command,
+                              msg.author.tag(),
+                              msg.author.id,
+                              msg.channel_id,
+                              why);
+
+    if suppressed > 0 {
+        content.push_str(&format!("\n{} more errors happened since the last report", suppressed));
+    }
+
+    if content.len() > 2000 {
+        let mut end = 1997;
+
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        content.truncate(end);
+        content.push_str("...");
+    }
+
+    actions::notify_owner(&ctx.data, "ERROR_LOG_CHANNEL_ID", &content, actions::DryRunPolicy::Send);
+}
@@ -6,11 +6,24 @@ use serenity::CACHE;
 use std::collections::HashMap;
 use std::fmt::Write;
 use std::sync::Arc;
-use super::misc::Uptime;
-use super::store::{EventCounter, ShardUptime};
+use super::actions;
+use super::logging;
+use super::misc::{self, permissions, Uptime};
+use super::scheduler;
+use super::store::{
+    EmojiUsageStore,
+    EventCounter,
+    GuildBlocklistStore,
+    ShardMessengers,
+    ShardUptime,
+    StickyRoleStore,
+    UnknownEvents,
+};
 
 macro_rules! reg {
     ($ctx:ident $name:expr) => {
+        let _log = logging::start_event($ctx.shard_id, $name);
+
         {
            let mut data = $ctx.data.lock();
             let counter = data.get_mut::<EventCounter>().unwrap();
@@ -51,10 +64,50 @@ impl EventHandler for Handler {
     }
 
     fn guild_create(&self, ctx: Context, guild: Guild, new: bool) {
+        reg!(ctx "GuildCreate");
+        logging::update(|c| c.guild = Some(guild.id.0));
+
         let status = if new { "new" } else { "old" };
         debug!("Received guild: {} ({})", guild.name, status);
 
-        reg!(ctx "GuildCreate");
+        let blocked = {
+            let data = ctx.data.lock();
+
+            data.get::<GuildBlocklistStore>().unwrap().guilds.contains(&guild.id.0)
+        };
+
+        if blocked {
+            info!("Leaving blocklisted guild {} ({})", guild.name, guild.id);
+
+            if let Err(why) = actions::leave_guild(&ctx, guild.id) {
+                warn!("Err leaving guild {}: {:?}", guild.id, why);
+            }
+
+            let content = format!("Left blocklisted guild:\n{}", describe_guild(&guild));
+            actions::notify_owner(&ctx.data, "GUILD_LOG_CHANNEL_ID", &content, actions::DryRunPolicy::Report);
+
+            return;
+        }
+
+        if new {
+            let content = format!("Joined guild:\n{}", describe_guild(&guild));
+            actions::notify_owner(&ctx.data, "GUILD_LOG_CHANNEL_ID", &content, actions::DryRunPolicy::Report);
+        }
+    }
+
+    fn guild_delete(&self, ctx: Context, partial: PartialGuild, full: Option<Arc<RwLock<Guild>>>) {
+        reg!(ctx "GuildDelete");
+        logging::update(|c| c.guild = Some(partial.id.0));
+
+        info!("Removed from guild {} ({})", partial.name, partial.id);
+
+        let description = match full {
+            Some(guild) => describe_guild(&guild.read()),
+            None => format!("{} ({})", partial.name, partial.id),
+        };
+
+        let content = format!("Left guild:\n{}", description);
+        actions::notify_owner(&ctx.data, "GUILD_LOG_CHANNEL_ID", &content, actions::DryRunPolicy::Report);
     }
 
     fn guild_emojis_update(&self, ctx: Context, _: GuildId, _: HashMap<EmojiId, Emoji>) {
@@ -67,6 +120,12 @@ impl EventHandler for Handler {
 
     fn guild_member_addition(&self, ctx: Context, guild_id: GuildId, mut member: Member) {
         reg!(ctx "GuildMemberAdd");
+        logging::update(|c| {
+            c.guild = Some(guild_id.0);
+            c.user = Some(member.user.read().id.0);
+        });
+
+        restore_sticky_roles(&ctx, &mut member);
 
         if guild_id == GUILD_DABBOT_ID {
             let user_id = member.user.read().id;
@@ -76,7 +135,7 @@ impl EventHandler for Handler {
                 None => return,
             };
 
-            let _ = ChannelId(301717945854197760).say(&diff);
+            let _ = actions::say(&ctx, Some(guild_id), ChannelId(301717945854197760), &diff);
 
             return;
         } else if guild_id == 381880193251409931 {
@@ -86,18 +145,40 @@ impl EventHandler for Handler {
                 381891844067557378
             };
 
-            if let Err(why) = member.add_role(role_id) {
+            if let Err(why) = actions::add_role(&ctx, &mut member, RoleId(role_id)) {
                 error!("Error adding role to {:?}: {:?}", member, why);
             }
         }
     }
 
-    fn guild_member_removal(&self, ctx: Context, _: GuildId, _: User, _: Option<Member>) {
+    fn guild_member_removal(&self, ctx: Context, guild_id: GuildId, user: User, member: Option<Member>) {
         reg!(ctx "GuildMemberRemoval");
+        logging::update(|c| {
+            c.guild = Some(guild_id.0);
+            c.user = Some(user.id.0);
+        });
+
+        let member = match member {
+            Some(member) => member,
+            None => return,
+        };
+
+        let role_ids = member.roles.iter().map(|id| id.0).collect::<Vec<_>>();
+
+        let mut data = ctx.data.lock();
+        let store = data.get_mut::<StickyRoleStore>().unwrap();
+
+        if store.record(guild_id.0, user.id.0, &role_ids, member.nick) {
+            store.save();
+        }
     }
 
     fn guild_member_update(&self, ctx: Context, old: Option<Member>, new: Member) {
         reg!(ctx "GuildMemberUpdate");
+        logging::update(|c| {
+            c.guild = Some(new.guild_id.0);
+            c.user = Some(new.user.read().id.0);
+        });
 
         if new.guild_id != GUILD_DABBOT_ID {
             return;
@@ -111,7 +192,7 @@ impl EventHandler for Handler {
             None => return,
         };
 
-        let _ = ChannelId(301717945854197760).say(&diff);
+        let _ = actions::say(&ctx, Some(new.guild_id), ChannelId(301717945854197760), &diff);
     }
 
     fn guild_members_chunk(&self, ctx: Context, _: GuildId, _: HashMap<UserId, Member>) {
@@ -134,8 +215,26 @@ impl EventHandler for Handler {
         reg!(ctx "GuildBanRemoval");
     }
 
-    fn message(&self, ctx: Context, _: Message) {
+    fn message(&self, ctx: Context, msg: Message) {
         reg!(ctx "MessageCreate");
+        logging::update(|c| {
+            c.channel = Some(msg.channel_id.0);
+            c.user = Some(msg.author.id.0);
+        });
+
+        if msg.author.bot {
+            return;
+        }
+
+        // An emoji repeated in a message only counts once.
+        let mut emoji_ids = misc::custom_emojis(&msg.content)
+            .into_iter()
+            .map(|emoji| EmojiId(emoji.id))
+            .collect::<Vec<_>>();
+        emoji_ids.sort();
+        emoji_ids.dedup();
+
+        record_emoji_usage(&ctx, msg.channel_id, &emoji_ids);
     }
 
     fn message_delete(&self, ctx: Context, _: ChannelId, _: MessageId) {
@@ -156,6 +255,10 @@ impl EventHandler for Handler {
 
     fn presence_update(&self, ctx: Context, event: PresenceUpdateEvent) {
         reg!(ctx "PresenceUpdate");
+        logging::update(|c| {
+            c.guild = event.guild_id.map(|id| id.0);
+            c.user = Some(event.presence.user_id.0);
+        });
 
         let guild_id = match event.guild_id {
             Some(guild_id) if guild_id == GUILD_DABBOT_ID => guild_id,
@@ -206,13 +309,21 @@ impl EventHandler for Handler {
             member.roles.retain(|r| r != role);
         }
 
-        if let Err(why) = member.add_roles(&[give]) {
+        if let Err(why) = actions::add_roles(&ctx, &mut member, &[give]) {
             warn!("Err updating member roles: {:?}", why);
         }
     }
 
-    fn reaction_add(&self, ctx: Context, _: Reaction) {
+    fn reaction_add(&self, ctx: Context, reaction: Reaction) {
         reg!(ctx "ReactionAdd");
+        logging::update(|c| {
+            c.channel = Some(reaction.channel_id.0);
+            c.user = Some(reaction.user_id.0);
+        });
+
+        if let ReactionType::Custom { id, .. } = reaction.emoji {
+            record_emoji_usage(&ctx, reaction.channel_id, &[id]);
+        }
     }
 
     fn reaction_remove(&self, ctx: Context, _: Reaction) {
@@ -224,6 +335,8 @@ impl EventHandler for Handler {
     }
 
     fn ready(&self, ctx: Context, ready: Ready) {
+        let _log = logging::start_event(ctx.shard_id, "Ready");
+
         if let Some(s) = ready.shard {
             info!("Logged in as '{}' on {}/{}",
                   ready.user.name,
@@ -233,7 +346,7 @@ impl EventHandler for Handler {
             info!("Logged in as '{}'", ready.user.name);
         }
 
-        let name = {
+        {
             let mut data = ctx.data.lock();
 
             {
@@ -242,31 +355,41 @@ impl EventHandler for Handler {
                 *entry += 1;
             }
 
-            let uptimes = data.get_mut::<ShardUptime>().unwrap();
-
             if let Some(shard) = ready.shard {
+                let uptimes = data.get_mut::<ShardUptime>().unwrap();
                 let entry = uptimes.entry(shard[0]).or_insert_with(Uptime::default);
                 entry.connect();
-
-                format!("nano help [{}/{}]", shard[0] + 1, shard[1])
-            } else {
-                "nano help".to_owned()
             }
-        };
 
-        ctx.set_game_name(&name);
+            data.get_mut::<ShardMessengers>().unwrap().insert(ctx.shard_id, ctx.shard.clone());
+        }
+
+        scheduler::update_presence(&ctx.data, Some(ctx.shard_id));
     }
 
     fn resume(&self, ctx: Context, _: ResumedEvent) {
         reg!(ctx "Resume");
+
+        let mut data = ctx.data.lock();
+        let uptimes = data.get_mut::<ShardUptime>().unwrap();
+        uptimes.entry(ctx.shard_id).or_insert_with(Uptime::default).resume();
     }
 
     fn typing_start(&self, ctx: Context, _: TypingStartEvent) {
         reg!(ctx "TypingStart");
     }
 
-    fn unknown(&self, _: Context, name: String, value: Value) {
+    fn unknown(&self, ctx: Context, name: String, value: Value) {
+        let _log = logging::start_event(ctx.shard_id, "Unknown");
+
         warn!("Received unknown event '{}': {:?}", name, value);
+
+        let mut data = ctx.data.lock();
+        let store = data.get_mut::<UnknownEvents>().unwrap();
+
+        if !store.record(name, value) {
+            warn!("Unknown event store is full; not recording");
+        }
     }
 
     fn voice_server_update(&self, ctx: Context, _: VoiceServerUpdateEvent) {
@@ -335,3 +458,138 @@ fn role_diff(guild_id: GuildId, user_id: UserId, old_roles: Vec<RoleId>, new_rol
 
     Some(content)
 }
+
+/// Counts uses of the guild's own custom emojis in the channel's guild.
+///
+/// Emojis from other guilds, which Nitro users can use anywhere, are ignored.
+fn record_emoji_usage(ctx: &Context, channel_id: ChannelId, emoji_ids: &[EmojiId]) {
+    if emoji_ids.is_empty() {
+        return;
+    }
+
+    let (guild_id, own_ids) = {
+        let cache = CACHE.read();
+
+        let guild_id = match cache.guild_channel(channel_id) {
+            Some(channel) => channel.read().guild_id,
+            None => return,
+        };
+
+        let own_ids = match cache.guild(guild_id) {
+            Some(guild) => {
+                let guild = guild.read();
+
+                emoji_ids.iter().filter(|id| guild.emojis.contains_key(id)).cloned().collect::<Vec<_>>()
+            },
+            None => return,
+        };
+
+        (guild_id, own_ids)
+    };
+
+    if own_ids.is_empty() {
+        return;
+    }
+
+    let mut data = ctx.data.lock();
+    let usage = data.get_mut::<EmojiUsageStore>().unwrap();
+
+    for id in own_ids {
+        usage.record(guild_id.0, id.0);
+    }
+}
+
+/// Gives a rejoining member back the sticky roles and nickname they had when
+/// they left, if they left within the guild's configured period.
+fn restore_sticky_roles(ctx: &Context, member: &mut Member) {
+    let user_id = member.user.read().id;
+
+    let saved = {
+        let mut data = ctx.data.lock();
+
+        data.get_mut::<StickyRoleStore>().unwrap().get(member.guild_id.0, user_id.0)
+    };
+
+    let saved = match saved {
+        Some(saved) => saved,
+        None => return,
+    };
+
+    // Roles may have been deleted while the member was gone, managed roles
+    // can't be given out manually, and the bot can only give out roles below
+    // its own highest role.
+    let role_ids = {
+        let guild = match member.guild_id.find() {
+            Some(guild) => guild,
+            None => return,
+        };
+        let guild = guild.read();
+        let bot_id = CACHE.read().user.id;
+        let bot_position = permissions::top_role_position(&guild, bot_id);
+
+        saved.roles
+            .iter()
+            .filter_map(|id| guild.roles.get(&RoleId(*id)))
+            .filter(|role| !role.managed && !member.roles.contains(&role.id))
+            .filter(|role| role.position < bot_position)
+            .map(|role| role.id)
+            .collect::<Vec<_>>()
+    };
+
+    let mut restored = true;
+
+    if !role_ids.is_empty() {
+        info!("Restoring {} sticky roles to {} in {}", role_ids.len(), user_id, member.guild_id);
+
+        if let Err(why) = actions::add_roles(ctx, member, &role_ids) {
+            warn!("Err restoring sticky roles to {}: {:?}", user_id, why);
+
+            restored = false;
+        }
+    }
+
+    if let Some(nick) = saved.nick {
+        if let Err(why) = actions::set_nickname(ctx, member.guild_id, user_id, &nick) {
+            warn!("Err restoring nickname of {}: {:?}", user_id, why);
+
+            restored = false;
+        }
+    }
+
+    if restored {
+        let mut data = ctx.data.lock();
+        let store = data.get_mut::<StickyRoleStore>().unwrap();
+
+        if store.remove(member.guild_id.0, user_id.0) {
+            store.save();
+        }
+    }
+}
+
+/// Formats a guild's name, member count, owner and bot ratio for join and
+/// leave notifications.
+fn describe_guild(guild: &Guild) -> String {
+    let owner = match CACHE.read().user(guild.owner_id) {
+        Some(user) => user.read().tag(),
+        None => "unknown".to_owned(),
+    };
+
+    // Only cached members can be checked for being a bot, which for large
+    // guilds isn't every member.
+    let bots = guild.members.values().filter(|m| m.user.read().bot).count();
+    let ratio = if guild.members.is_empty() {
+        0.0
+    } else {
+        bots as f64 / guild.members.len() as f64 * 100.0
+    };
+
+    format!("**{}** ({})\nMembers: {}\nOwner: {} ({})\nBots: {}/{} cached ({:.1}%)",
+            guild.name,
+            guild.id,
+            guild.member_count,
+            owner,
+            guild.owner_id,
+            bots,
+            guild.members.len(),
+            ratio)
+}
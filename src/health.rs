@@ -0,0 +1,188 @@
+//! An optional HTTP server for orchestrators and dashboards to probe.
+//!
+//! It's started when the `HEALTH_ADDR` env var is set to an address to listen
+//! on, such as `127.0.0.1:8080`, and serves:
+//!
+//! - `/healthz`: 200 as long as the process is running;
+//! - `/readyz`: 200 when every shard is connected and has had a heartbeat ACK
+//!   within the last `READY_ACK_SECS` seconds, otherwise 503;
+//! - `/shards`: the state of each shard as JSON.
+
+use chrono::{DateTime, Duration, Utc};
+use hyper::header::ContentType;
+use hyper::server::{Request, Response, Server};
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+use serde_json;
+use serenity::client::bridge::gateway::ShardId;
+use serenity::gateway::ConnectionStage;
+use serenity::prelude::Mutex;
+use serenity::CACHE;
+use std::env;
+use std::sync::Arc;
+use std::thread;
+use typemap::ShareMap;
+use ::store::{ShardHealth, ShardManagerContainer, ShardUptime};
+
+/// The default longest time since a shard's last heartbeat ACK for it to be
+/// considered ready, in seconds. Overridden by the `READY_ACK_SECS` env var.
+const READY_ACK_SECS: i64 = 120;
+
+#[derive(Serialize)]
+struct ShardInfo {
+    id: u64,
+    stage: String,
+    connected: bool,
+    latency_ms: Option<u64>,
+    last_ack: Option<DateTime<Utc>>,
+    connected_since: Option<DateTime<Utc>>,
+    reconnects: Option<u64>,
+    last_resume: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    /// Why the bot isn't ready, if it isn't.
+    problems: Vec<String>,
+}
+
+/// Starts the server if `HEALTH_ADDR` is set.
+pub fn start(data: Arc<Mutex<ShareMap>>) {
+    let addr = match env::var("HEALTH_ADDR") {
+        Ok(addr) => addr,
+        Err(_) => return,
+    };
+
+    let res = thread::Builder::new()
+        .name("health".to_owned())
+        .spawn(move || {
+            let server = match Server::http(&addr[..]) {
+                Ok(server) => server,
+                Err(why) => {
+                    error!("Err binding health server to {}: {:?}", addr, why);
+
+                    return;
+                },
+            };
+
+            info!("Health server listening on {}", addr);
+
+            if let Err(why) = server.handle(move |req: Request, res: Response| handle(&data, req, res)) {
+                error!("Err running health server: {:?}", why);
+            }
+        });
+
+    if let Err(why) = res {
+        error!("Err starting health server: {:?}", why);
+    }
+}
+
+fn handle(data: &Mutex<ShareMap>, req: Request, mut res: Response) {
+    let path = match req.uri {
+        RequestUri::AbsolutePath(ref path) => path.split('?').next().unwrap_or("").to_owned(),
+        _ => String::new(),
+    };
+
+    let (status, body) = match &path[..] {
+        "/healthz" => (StatusCode::Ok, "{\"alive\":true}".to_owned()),
+        "/readyz" => {
+            let readiness = readiness(&shards(data));
+            let status = if readiness.ready {
+                StatusCode::Ok
+            } else {
+                StatusCode::ServiceUnavailable
+            };
+
+            (status, serde_json::to_string(&readiness).unwrap_or_default())
+        },
+        "/shards" => (StatusCode::Ok, serde_json::to_string(&shards(data)).unwrap_or_default()),
+        _ => (StatusCode::NotFound, "{\"error\":\"not found\"}".to_owned()),
+    };
+
+    *res.status_mut() = status;
+    res.headers_mut().set(ContentType::json());
+
+    if let Err(why) = res.send(body.as_bytes()) {
+        warn!("Err responding to health check: {:?}", why);
+    }
+}
+
+fn shards(data: &Mutex<ShareMap>) -> Vec<ShardInfo> {
+    let shard_count = CACHE.read().shard_count;
+
+    let (manager, mut shards) = {
+        let data = data.lock();
+        let manager = Arc::clone(data.get::<ShardManagerContainer>().unwrap());
+        let health = data.get::<ShardHealth>().unwrap();
+        let uptimes = data.get::<ShardUptime>().unwrap();
+
+        let shards = (0..shard_count).map(|id| {
+            let uptime = uptimes.get(&id);
+
+            ShardInfo {
+                id: id,
+                stage: "NotRunning".to_owned(),
+                connected: false,
+                latency_ms: None,
+                last_ack: health.get(&id).and_then(|status| status.last_ack),
+                connected_since: uptime.map(|uptime| uptime.connection),
+                reconnects: uptime.map(|uptime| uptime.reconnects),
+                last_resume: uptime.and_then(|uptime| uptime.last_resume),
+            }
+        }).collect::<Vec<_>>();
+
+        (manager, shards)
+    };
+
+    let manager = manager.lock();
+    let runners = manager.runners.lock();
+
+    for shard in &mut shards {
+        if let Some(runner) = runners.get(&ShardId(shard.id)) {
+            shard.stage = format!("{:?}", runner.stage);
+            shard.connected = match runner.stage {
+                ConnectionStage::Connected => true,
+                _ => false,
+            };
+            shard.latency_ms = runner.latency.map(|latency| {
+                latency.as_secs() * 1000 + u64::from(latency.subsec_nanos()) / 1_000_000
+            });
+        }
+    }
+
+    shards
+}
+
+fn readiness(shards: &[ShardInfo]) -> Readiness {
+    let threshold = env::var("READY_ACK_SECS")
+        .ok()
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(READY_ACK_SECS);
+    let cutoff = Utc::now() - Duration::seconds(threshold);
+
+    let mut problems = vec![];
+
+    if shards.is_empty() {
+        problems.push("No shards have started".to_owned());
+    }
+
+    for shard in shards {
+        if !shard.connected {
+            problems.push(format!("Shard {} is {}", shard.id, shard.stage));
+        } else if shard.connected_since.is_none() {
+            problems.push(format!("Shard {} hasn't received a ready", shard.id));
+        }
+
+        match shard.last_ack {
+            Some(at) if at >= cutoff => {},
+            Some(at) => problems.push(format!("Shard {} last had a heartbeat ACK at {}", shard.id, at.to_rfc3339())),
+            None => problems.push(format!("Shard {} hasn't had a heartbeat ACK", shard.id)),
+        }
+    }
+
+    Readiness {
+        ready: problems.is_empty(),
+        problems: problems,
+    }
+}
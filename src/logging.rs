@@ -0,0 +1,156 @@
+//! Sets up logging, optionally as JSON for log collectors.
+//!
+//! Logs are free text from `env_logger` by default. With `LOG_FORMAT=json`,
+//! each record is written as a JSON object on its own line instead, carrying
+//! the context of the command or event being handled on the thread that
+//! logged it: the shard, guild, channel and user IDs, the command or event
+//! name, and an ID unique to the invocation. `RUST_LOG` can then only be a
+//! single level, such as `debug`.
+
+use chrono::Utc;
+use env_logger;
+use log::{self, Log, LogLevelFilter, LogMetadata, LogRecord};
+use serde_json;
+use std::cell::RefCell;
+use std::env;
+use std::io::{self, Write};
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
+
+static NEXT_INVOCATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+thread_local! {
+    static CONTEXT: RefCell<LogContext> = RefCell::new(LogContext::default());
+}
+
+/// What a thread is handling, attached to the records it logs.
+#[derive(Clone, Debug, Default)]
+pub struct LogContext {
+    pub shard: Option<u64>,
+    pub guild: Option<u64>,
+    pub channel: Option<u64>,
+    pub user: Option<u64>,
+    pub command: Option<String>,
+    pub event: Option<&'static str>,
+    pub invocation: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    timestamp: String,
+    level: String,
+    module: &'a str,
+    message: String,
+    shard: Option<u64>,
+    guild: Option<u64>,
+    channel: Option<u64>,
+    user: Option<u64>,
+    command: Option<String>,
+    event: Option<&'static str>,
+    invocation: Option<usize>,
+}
+
+struct JsonLogger {
+    level: LogLevelFilter,
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let context = CONTEXT.with(|context| context.borrow().clone());
+        let line = Line {
+            timestamp: Utc::now().to_rfc3339(),
+            level: record.level().to_string(),
+            module: record.location().module_path(),
+            message: record.args().to_string(),
+            shard: context.shard,
+            guild: context.guild,
+            channel: context.channel,
+            user: context.user,
+            command: context.command,
+            event: context.event,
+            invocation: context.invocation,
+        };
+
+        if let Ok(json) = serde_json::to_string(&line) {
+            let _ = writeln!(io::stderr(), "{}", json);
+        }
+    }
+}
+
+pub fn init() {
+    if env::var("LOG_FORMAT").map(|x| x == "json").unwrap_or(false) {
+        let level = env::var("RUST_LOG")
+            .ok()
+            .and_then(|x| x.parse::<LogLevelFilter>().ok())
+            .unwrap_or(LogLevelFilter::Info);
+
+        log::set_logger(|max_level| {
+            max_level.set(level);
+
+            Box::new(JsonLogger {
+                level: level,
+            })
+        }).expect("json logger");
+    } else {
+        env_logger::init().expect("env logger");
+    }
+}
+
+/// Sets the context for a command invocation, until `clear` is called.
+pub fn start_command(shard: u64, guild: Option<u64>, channel: u64, user: u64, command: &str) {
+    set(LogContext {
+        shard: Some(shard),
+        guild: guild,
+        channel: Some(channel),
+        user: Some(user),
+        command: Some(command.to_owned()),
+        event: None,
+        invocation: Some(next_invocation()),
+    });
+}
+
+/// Sets the context for handling an event, until the returned guard is
+/// dropped.
+pub fn start_event(shard: u64, event: &'static str) -> Guard {
+    set(LogContext {
+        shard: Some(shard),
+        event: Some(event),
+        invocation: Some(next_invocation()),
+        ..Default::default()
+    });
+
+    Guard
+}
+
+/// Adds to the current context, e.g. once an event's guild is known.
+pub fn update<F: FnOnce(&mut LogContext)>(f: F) {
+    CONTEXT.with(|context| f(&mut context.borrow_mut()));
+}
+
+pub fn clear() {
+    set(LogContext::default());
+}
+
+/// Clears the context when dropped.
+pub struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        clear();
+    }
+}
+
+fn set(new: LogContext) {
+    CONTEXT.with(|context| *context.borrow_mut() = new);
+}
+
+fn next_invocation() -> usize {
+    NEXT_INVOCATION.fetch_add(1, Ordering::Relaxed)
+}
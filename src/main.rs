@@ -1,6 +1,8 @@
 #[macro_use] extern crate lazy_static;
 #[macro_use] extern crate log;
+#[macro_use] extern crate serde_derive;
 
+extern crate base64;
 extern crate chrono;
 extern crate dotenv;
 extern crate env_logger;
@@ -15,25 +17,77 @@ extern crate serde_json;
 extern crate serde;
 extern crate serenity;
 extern crate typemap;
+extern crate unicode_names2;
 extern crate urbandictionary;
 
 #[macro_use] mod utils;
 
+mod actions;
 mod commands;
+mod error;
 mod event;
+mod health;
+mod logging;
 mod misc;
 mod prelude;
+mod resolver;
+mod scheduler;
 mod store;
 
-use serenity::client::{Client, rest};
-use serenity::framework::standard::{StandardFramework, help_commands};
+use serenity::client::{Client, CACHE, rest};
+use serenity::framework::standard::StandardFramework;
+use serenity::model::Permissions;
 use std::env;
 use std::collections::{HashMap, HashSet};
-use store::{CommandCounter, CustomCache, EventCounter, NanoCache, ShardUptime};
+use std::time::Instant;
+use actions::DryRun;
+use error::{Guarded, Reports};
+use misc::{
+    AuditLog,
+    BootHistory,
+    CommandLatency,
+    CommandToggles,
+    CommandUsage,
+    Cooldowns,
+    EmojiUsage,
+    GuildBlocklist,
+    Presence,
+    StickyRoles,
+    TempRoles,
+    UnknownEventStore,
+};
+use store::{
+    AuditLogStore,
+    BootHistoryStore,
+    CommandCounter,
+    CommandTimings,
+    CommandToggleStore,
+    CooldownStore,
+    CpuSample,
+    CustomCache,
+    DryRunConfig,
+    EmojiUsageStore,
+    ErrorReports,
+    EventCounter,
+    GuildBlocklistStore,
+    NanoCache,
+    PresenceStore,
+    ShardMessengers,
+    ShardHealth,
+    ShardManagerContainer,
+    ShardUptime,
+    StickyRoleStore,
+    TempRoleStore,
+    UnknownEvents,
+};
+
+/// The default time over which a command invocation is logged as slow, in
+/// milliseconds. Overridden by the `SLOW_COMMAND_MS` env var.
+const SLOW_COMMAND_MS: u64 = 5000;
 
 fn main() {
     dotenv::dotenv().expect("init dotenv");
-    env_logger::init().expect("env logger");
+    logging::init();
 
     let mut client = Client::new(
         &env::var("DISCORD_TOKEN").expect("no token present"),
@@ -42,10 +96,27 @@ fn main() {
 
     {
         let mut data = client.data.lock();
-        data.insert::<CommandCounter>(HashMap::default());
+        data.insert::<AuditLogStore>(AuditLog::load());
+        data.insert::<BootHistoryStore>(BootHistory::load());
+        data.insert::<CommandCounter>(CommandUsage::load());
+        data.insert::<CommandTimings>(CommandLatency::default());
+        data.insert::<CommandToggleStore>(CommandToggles::load());
+        data.insert::<CooldownStore>(Cooldowns::load());
+        data.insert::<CpuSample>((Instant::now(), 0.0));
+        data.insert::<DryRunConfig>(DryRun::load());
+        data.insert::<EmojiUsageStore>(EmojiUsage::load());
+        data.insert::<ErrorReports>(Reports::default());
         data.insert::<EventCounter>(HashMap::default());
+        data.insert::<GuildBlocklistStore>(GuildBlocklist::load());
         data.insert::<NanoCache>(CustomCache::default());
+        data.insert::<PresenceStore>(Presence::load());
+        data.insert::<ShardMessengers>(HashMap::default());
+        data.insert::<ShardHealth>(HashMap::default());
+        data.insert::<ShardManagerContainer>(client.shard_manager.clone());
         data.insert::<ShardUptime>(HashMap::default());
+        data.insert::<StickyRoleStore>(StickyRoles::load());
+        data.insert::<TempRoleStore>(TempRoles::load());
+        data.insert::<UnknownEvents>(UnknownEventStore::load());
     }
 
     let owners = {
@@ -67,6 +138,9 @@ fn main() {
         set
     };
 
+    scheduler::start(client.data.clone());
+    health::start(client.data.clone());
+
     client.with_framework(StandardFramework::new()
         .configure(|c| c
             .allow_whitespace(true)
@@ -74,95 +148,233 @@ fn main() {
             .owners(owners)
             .prefixes(vec!["nano"]))
         .before(|context, message, command_name| {
+            let name = commands::canonical_name(command_name);
+            let guild_id = CACHE.read()
+                .guild_channel(message.channel_id)
+                .map(|c| c.read().guild_id.0);
+
+            logging::start_command(context.shard_id, guild_id, message.channel_id.0, message.author.id.0, name);
             info!("{} used command '{}'", message.author.name, command_name);
 
             let mut data = context.data.lock();
-            let counter = data.get_mut::<CommandCounter>().unwrap();
-            let entry = counter.entry(command_name.to_owned()).or_insert(0);
-            *entry += 1;
+
+            if commands::is_toggleable(name) {
+                let toggles = data.get::<CommandToggleStore>().unwrap();
+
+                if !toggles.enabled(guild_id, message.channel_id.0, name, commands::group_of(name)) {
+                    debug!("'{}' is disabled in {}", name, message.channel_id);
+                    logging::clear();
+
+                    return false;
+                }
+            }
+
+            let owner_id = data.get::<NanoCache>().unwrap().owner_id;
+            let cooldown = data.get_mut::<CooldownStore>().unwrap().take(name,
+                                                                          message.author.id.0,
+                                                                          message.channel_id.0,
+                                                                          guild_id,
+                                                                          message.author.id == owner_id);
+
+            if let Err(limited) = cooldown {
+                drop(data);
+
+                debug!("'{}' is on cooldown for {} for {}s", name, message.author.id, limited.retry_after);
+
+                if limited.notify {
+                    let content = format!("Slow down! You can use `{}` again in {}s", name, limited.retry_after);
+                    let _ = message.channel_id.say(&content);
+                }
+
+                logging::clear();
+
+                return false;
+            }
+
+            data.get_mut::<CommandCounter>().unwrap().record(name, guild_id, message.author.id.0);
+            data.get_mut::<CommandTimings>().unwrap().start(message.id.0);
 
             true
         })
-        .command("udefined", |c| c.cmd(commands::conversation::UdefineCommand))
-        .help(help_commands::with_embeds)
+        .after(|context, message, command_name, res| {
+            let name = commands::canonical_name(command_name);
+            let elapsed = context.data.lock().get_mut::<CommandTimings>().unwrap().finish(message.id.0, name);
+
+            if let Some(elapsed) = elapsed {
+                let threshold = env::var("SLOW_COMMAND_MS")
+                    .ok()
+                    .and_then(|x| x.parse::<u64>().ok())
+                    .unwrap_or(SLOW_COMMAND_MS);
+
+                if elapsed > threshold {
+                    warn!("Slow command '{}' took {}ms for {} in {}: {:?}",
+                          name,
+                          elapsed,
+                          message.author.id,
+                          message.channel_id,
+                          message.content);
+                }
+            }
+
+            if let Err(why) = res {
+                error::handle(context, message, name, why.into());
+            }
+
+            logging::clear();
+        })
+        .command(commands::toggleable("udefined", None), |c| c.cmd(Guarded(commands::conversation::UdefineCommand)))
+        .help(commands::meta::help)
         .group("Luck", |g| g
-            .command("8ball", |c| c
-                .cmd(commands::random::MagicEightBallCommand))
-            .command("choose", |c| c
-                .cmd(commands::random::ChooseCommand))
-            .command("coinflip", |c| c
-                .cmd(commands::random::CoinflipCommand))
-            .command("roll", |c| c
-                .cmd(commands::random::RollCommand))
-            .command("roulette", |c| c
-                .cmd(commands::random::RouletteCommand)))
+            .command(commands::toggleable("8ball", Some("Luck")), |c| c
+                .cmd(Guarded(commands::random::MagicEightBallCommand)))
+            .command(commands::toggleable("choose", Some("Luck")), |c| c
+                .cmd(Guarded(commands::random::ChooseCommand)))
+            .command(commands::toggleable("coinflip", Some("Luck")), |c| c
+                .cmd(Guarded(commands::random::CoinflipCommand)))
+            .command(commands::toggleable("roll", Some("Luck")), |c| c
+                .cmd(Guarded(commands::random::RollCommand)))
+            .command(commands::toggleable("roulette", Some("Luck")), |c| c
+                .cmd(Guarded(commands::random::RouletteCommand))))
         .group("Media", |g| g
-            .command("anime", |c| c
+            .command(commands::toggleable("anime", Some("Media")), |c| c
                 .known_as("animu")
-                .cmd(commands::media::AnimeCommand)))
+                .cmd(Guarded(commands::media::AnimeCommand))))
         .group("Meta", |g| g
-            .command("avatar", |c| c
-                .cmd(commands::meta::AvatarCommand))
+            .command(commands::toggleable("avatar", Some("Meta")), |c| c
+                .cmd(Guarded(commands::meta::AvatarCommand)))
+            .command(commands::toggleable("channelinfo", Some("Meta")), |c| c
+                .cmd(Guarded(commands::meta::ChannelInfoCommand))
+                .guild_only(true))
             .command("rping", |c| c
-                .cmd(commands::meta::RpingCommand)
+                .cmd(Guarded(commands::meta::RpingCommand))
                 .help_available(false)
                 .owners_only(true))
+            .command(commands::toggleable("emoji", Some("Meta")), |c| c
+                .cmd(Guarded(commands::meta::EmojiCommand)))
+            .command(commands::toggleable("emojistats", Some("Meta")), |c| c
+                .cmd(Guarded(commands::meta::EmojiStatsCommand))
+                .guild_only(true))
             .command("gping", |c| c
-                .cmd(commands::meta::GpingCommand)
+                .cmd(Guarded(commands::meta::GpingCommand))
                 .help_available(false)
                 .owners_only(true))
-            .command("roleinfo", |c| c
-                .cmd(commands::meta::RoleInfoCommand))
-            .command("uptime", |c| c
-                .cmd(commands::meta::UptimeCommand))
-            .command("userinfo", |c| c
+            .command(commands::toggleable("perms", Some("Meta")), |c| c
+                .cmd(Guarded(commands::meta::PermsCommand))
+                .guild_only(true))
+            .command(commands::toggleable("roleinfo", Some("Meta")), |c| c
+                .cmd(Guarded(commands::meta::RoleInfoCommand)))
+            .command(commands::toggleable("serverinfo", Some("Meta")), |c| c
+                .known_as("guildinfo")
+                .cmd(Guarded(commands::meta::ServerInfoCommand))
+                .guild_only(true))
+            .command(commands::toggleable("snowflake", Some("Meta")), |c| c
+                .known_as("id")
+                .cmd(Guarded(commands::meta::SnowflakeCommand)))
+            .command(commands::toggleable("uptime", Some("Meta")), |c| c
+                .cmd(Guarded(commands::meta::UptimeCommand)))
+            .command(commands::toggleable("userinfo", Some("Meta")), |c| c
                 .known_as("me")
-                .cmd(commands::meta::UserInfoCommand)))
+                .cmd(Guarded(commands::meta::UserInfoCommand))))
         .group("Misc", |g| g
-            .command("aes", |c| c
-                .cmd(commands::misc::AesCommand))
-            .command("aescaps", |c| c
-                .cmd(commands::misc::AesCapsCommand))
-            .command("aesthetic", |c| c
-                .cmd(commands::misc::AesCommand))
-            .command("aestheticcaps", |c| c
-                .cmd(commands::misc::AesCapsCommand))
-            .command("hello", |c| c
-                .cmd(commands::misc::HelloCommand))
-            .command("mfw", |c| c
-                .cmd(commands::misc::MfwCommand))
-            .command("pi", |c| c
-                .cmd(commands::misc::PiCommand)))
-        .command("modping", |c| c
-            .cmd(commands::conversation::ModPingCommand)
+            .command(commands::toggleable("aes", Some("Misc")), |c| c
+                .cmd(Guarded(commands::misc::AesCommand)))
+            .command(commands::toggleable("aescaps", Some("Misc")), |c| c
+                .cmd(Guarded(commands::misc::AesCapsCommand)))
+            .command(commands::toggleable("aesthetic", Some("Misc")), |c| c
+                .cmd(Guarded(commands::misc::AesCommand)))
+            .command(commands::toggleable("aestheticcaps", Some("Misc")), |c| c
+                .cmd(Guarded(commands::misc::AesCapsCommand)))
+            .command(commands::toggleable("hello", Some("Misc")), |c| c
+                .cmd(Guarded(commands::misc::HelloCommand)))
+            .command(commands::toggleable("mfw", Some("Misc")), |c| c
+                .cmd(Guarded(commands::misc::MfwCommand)))
+            .command(commands::toggleable("pi", Some("Misc")), |c| c
+                .cmd(Guarded(commands::misc::PiCommand))))
+        .group("Moderation", |g| g
+            .command(commands::toggleable("sticky", Some("Moderation")), |c| c
+                .cmd(Guarded(commands::moderation::StickyCommand))
+                .guild_only(true)
+                .required_permissions(Permissions::MANAGE_ROLES))
+            .command(commands::toggleable("temprole", Some("Moderation")), |c| c
+                .cmd(Guarded(commands::moderation::TempRoleCommand))
+                .guild_only(true)
+                .required_permissions(Permissions::MANAGE_ROLES))
+            .command("toggle", |c| c
+                .cmd(Guarded(commands::moderation::ToggleCommand))
+                .guild_only(true)
+                .required_permissions(Permissions::MANAGE_GUILD)))
+        .command(commands::toggleable("modping", None), |c| c
+            .cmd(Guarded(commands::conversation::ModPingCommand))
             .guild_only(true)
             .help_available(false)
             .known_as("pingmod"))
+        .command("blocklist", |c| c
+            .cmd(Guarded(commands::owner::BlocklistCommand))
+            .help_available(false)
+            .owners_only(true))
         .command("commands", |c| c
-            .cmd(commands::owner::CommandsCommand)
+            .cmd(Guarded(commands::owner::CommandsCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("cooldown", |c| c
+            .cmd(Guarded(commands::owner::CooldownCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("dryrun", |c| c
+            .cmd(Guarded(commands::owner::DryRunCommand))
             .help_available(false)
             .owners_only(true))
         .command("eval", |c| c
-            .cmd(commands::owner::EvalCommand)
+            .cmd(Guarded(commands::owner::EvalCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("guilds", |c| c
+            .cmd(Guarded(commands::owner::GuildsCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("leave", |c| c
+            .cmd(Guarded(commands::owner::LeaveCommand))
             .help_available(false)
             .owners_only(true))
         .command("stats", |c| c
-            .cmd(commands::owner::StatsCommand)
+            .cmd(Guarded(commands::owner::StatsCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("timings", |c| c
+            .cmd(Guarded(commands::owner::TimingsCommand))
             .help_available(false)
             .owners_only(true))
         .command("events", |c| c
-            .cmd(commands::owner::EventsCommand)
+            .cmd(Guarded(commands::owner::EventsCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("unknown", |c| c
+            .cmd(Guarded(commands::owner::UnknownCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("set avatar", |c| c
+            .cmd(Guarded(commands::owner::SetAvatarCommand))
             .help_available(false)
             .owners_only(true))
         .command("set name", |c| c
-            .cmd(commands::owner::SetNameCommand)
+            .cmd(Guarded(commands::owner::SetNameCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("set activity", |c| c
+            .cmd(Guarded(commands::owner::SetActivityCommand))
+            .help_available(false)
+            .owners_only(true))
+        .command("set rotation", |c| c
+            .cmd(Guarded(commands::owner::SetRotationCommand))
             .help_available(false)
             .owners_only(true))
         .command("set status", |c| c
-            .cmd(commands::owner::SetStatusCommand)
+            .cmd(Guarded(commands::owner::SetStatusCommand))
             .help_available(false)
             .owners_only(true)));
 
+    commands::check_toggleable();
+
     if let Err(why) = client.start_autosharded() {
         error!("Err starting client: {:?}", why);
     }
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use super::persist;
+
+const FILE_NAME: &'static str = "audit_log.json";
+/// The maximum number of entries kept per guild; older entries are dropped
+/// first.
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub description: String,
+}
+
+/// A capped per-guild record of actions the bot took on its own.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AuditLog {
+    pub guilds: HashMap<u64, VecDeque<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+
+    /// Adds an entry for the guild and saves the log.
+    pub fn record(&mut self, guild_id: u64, description: String) {
+        info!("[audit] {}: {}", guild_id, description);
+
+        let entries = self.guilds.entry(guild_id).or_insert_with(VecDeque::new);
+
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        }
+
+        entries.push_back(AuditEntry {
+            at: Utc::now(),
+            description: description,
+        });
+
+        self.save();
+    }
+}
@@ -0,0 +1,42 @@
+use base64;
+use super::persist;
+
+const FILE_NAME: &'static str = "previous_avatar.json";
+/// The largest image that's accepted as an avatar, in bytes.
+pub const MAX_SIZE: usize = 8 * 1024 * 1024;
+
+/// Works out an image's MIME type from its magic bytes, if it's a type that
+/// Discord accepts for avatars.
+pub fn image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else {
+        None
+    }
+}
+
+/// Encodes an image as the data URI that `edit_profile` expects.
+pub fn data_uri(bytes: &[u8]) -> Option<String> {
+    image_type(bytes).map(|mime| format!("data:{};base64,{}", mime, base64::encode(bytes)))
+}
+
+/// The avatar the bot had before it was last changed, as a data URI, so the
+/// change can be reverted.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PreviousAvatar {
+    pub data: Option<String>,
+}
+
+impl PreviousAvatar {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+}
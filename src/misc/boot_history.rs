@@ -0,0 +1,80 @@
+use chrono::{DateTime, Duration, Utc};
+use std::cmp;
+use super::persist;
+
+const FILE_NAME: &'static str = "boot_history.json";
+/// How long sessions are kept for, in days.
+const KEEP_DAYS: i64 = 30;
+
+/// A span of time that the bot was running for.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Session {
+    pub start: DateTime<Utc>,
+    /// When the session was last known to be running.
+    pub last_seen: DateTime<Utc>,
+}
+
+/// The times the bot has been running for, used to work out its uptime over
+/// the last days.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BootHistory {
+    pub sessions: Vec<Session>,
+}
+
+impl BootHistory {
+    /// Loads the history and starts a new session for this process.
+    pub fn load() -> Self {
+        let mut history: BootHistory = persist::load(FILE_NAME);
+        let now = Utc::now();
+
+        history.sessions.push(Session {
+            start: now,
+            last_seen: now,
+        });
+        history.save();
+
+        history
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+
+    /// Marks the current session as still running, dropping sessions that are
+    /// too old to matter.
+    pub fn touch(&mut self) {
+        let now = Utc::now();
+        let cutoff = now - Duration::days(KEEP_DAYS);
+
+        if let Some(session) = self.sessions.last_mut() {
+            session.last_seen = now;
+        }
+
+        self.sessions.retain(|session| session.last_seen > cutoff);
+    }
+
+    /// The percentage of time the bot was running over the last `days`, or
+    /// since it was first started if that's more recent.
+    pub fn uptime_percent(&self, days: i64) -> f64 {
+        let now = Utc::now();
+        let first = match self.sessions.first() {
+            Some(session) => session.start,
+            None => return 0.0,
+        };
+        let window_start = cmp::max(now - Duration::days(days), first);
+        let window = (now - window_start).num_seconds();
+
+        if window <= 0 {
+            return 100.0;
+        }
+
+        let up = self.sessions.iter().fold(0, |acc, session| {
+            let start = cmp::max(session.start, window_start);
+            let end = cmp::min(session.last_seen, now);
+
+            acc + cmp::max((end - start).num_seconds(), 0)
+        });
+
+        (up as f64 / window as f64 * 100.0).min(100.0)
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// How many of the most recent timings percentiles are worked out from.
+const MAX_SAMPLES: usize = 1000;
+/// How long an invocation is waited on before it's assumed it'll never
+/// finish.
+const PENDING_TIMEOUT: u64 = 3600;
+
+/// How long a command has taken to run, in milliseconds.
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub total: u64,
+    pub max: u64,
+    samples: VecDeque<u64>,
+}
+
+impl LatencyStats {
+    pub fn mean(&self) -> u64 {
+        if self.count == 0 { 0 } else { self.total / self.count }
+    }
+
+    /// The given percentile of the most recent timings.
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        if self.samples.is_empty() {
+            return 0;
+        }
+
+        let mut samples = self.samples.iter().cloned().collect::<Vec<_>>();
+        samples.sort();
+
+        let index = ((samples.len() as f64 * percentile).ceil() as usize).max(1) - 1;
+
+        samples[index]
+    }
+
+    fn record(&mut self, ms: u64) {
+        self.count += 1;
+        self.total += ms;
+        self.max = self.max.max(ms);
+
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(ms);
+    }
+}
+
+/// Times command invocations from the `before` hook to the `after` hook.
+#[derive(Debug, Default)]
+pub struct CommandLatency {
+    pub commands: HashMap<String, LatencyStats>,
+    /// When each running invocation started, by the ID of its message.
+    pending: HashMap<u64, Instant>,
+}
+
+impl CommandLatency {
+    pub fn start(&mut self, message_id: u64) {
+        self.pending.insert(message_id, Instant::now());
+    }
+
+    /// Records how long the invocation took, returning it in milliseconds.
+    pub fn finish(&mut self, message_id: u64, command: &str) -> Option<u64> {
+        let elapsed = self.pending.remove(&message_id)?.elapsed();
+        let ms = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_nanos()) / 1_000_000;
+
+        self.commands.entry(command.to_owned()).or_insert_with(Default::default).record(ms);
+
+        Some(ms)
+    }
+
+    /// Forgets invocations that have been running for too long to still be
+    /// waited on.
+    pub fn prune(&mut self) {
+        let timeout = Duration::from_secs(PENDING_TIMEOUT);
+
+        self.pending.retain(|_, at| at.elapsed() < timeout);
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use super::persist;
+
+const FILE_NAME: &'static str = "command_toggles.json";
+
+/// Commands and groups that are explicitly enabled or disabled somewhere.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ToggleRules {
+    pub commands: HashMap<String, bool>,
+    pub groups: HashMap<String, bool>,
+}
+
+impl ToggleRules {
+    /// Whether the command is enabled here, if a rule says either way.
+    ///
+    /// A rule for the command itself takes precedence over one for its group.
+    pub fn get(&self, command: &str, group: Option<&str>) -> Option<bool> {
+        self.commands
+            .get(command)
+            .or_else(|| group.and_then(|group| self.groups.get(group)))
+            .cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty() && self.groups.is_empty()
+    }
+}
+
+/// Per-guild and per-channel rules for which commands can be used.
+///
+/// Channel rules take precedence over guild rules, so a command can be
+/// disabled in a guild but enabled in a single channel. Commands without any
+/// rule are enabled.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CommandToggles {
+    pub guilds: HashMap<u64, ToggleRules>,
+    pub channels: HashMap<u64, ToggleRules>,
+}
+
+impl CommandToggles {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&mut self) {
+        self.guilds.retain(|_, rules| !rules.is_empty());
+        self.channels.retain(|_, rules| !rules.is_empty());
+
+        persist::save(FILE_NAME, self);
+    }
+
+    pub fn enabled(&self, guild_id: Option<u64>, channel_id: u64, command: &str, group: Option<&str>)
+        -> bool {
+        self.channels
+            .get(&channel_id)
+            .and_then(|rules| rules.get(command, group))
+            .or_else(|| guild_id
+                .and_then(|id| self.guilds.get(&id))
+                .and_then(|rules| rules.get(command, group)))
+            .unwrap_or(true)
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::{Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+use super::persist;
+
+const FILE_NAME: &'static str = "command_usage.json";
+/// How many days of usage are kept.
+const RETENTION_DAYS: i64 = 90;
+
+/// How many times a user used a command in a guild on a day.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UsageRow {
+    pub day: NaiveDate,
+    pub command: String,
+    /// The guild the command was used in, or `None` in DMs.
+    pub guild_id: Option<u64>,
+    pub user_id: u64,
+    pub count: u64,
+}
+
+/// What usage rows are grouped by when summarizing them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GroupBy {
+    Command,
+    Day,
+    Guild,
+    User,
+}
+
+#[derive(Debug, Default)]
+pub struct UsageFilter {
+    pub guild_id: Option<u64>,
+    pub user_id: Option<u64>,
+    pub since: Option<NaiveDate>,
+}
+
+impl UsageFilter {
+    pub fn matches(&self, row: &UsageRow) -> bool {
+        self.guild_id.map_or(true, |id| row.guild_id == Some(id))
+            && self.user_id.map_or(true, |id| row.user_id == id)
+            && self.since.map_or(true, |since| row.day >= since)
+    }
+}
+
+/// Persistent command usage, counted per command, guild, user and day.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CommandUsage {
+    pub rows: Vec<UsageRow>,
+    /// Indices into `rows` by day, command, guild and user.
+    #[serde(skip)]
+    index: HashMap<(NaiveDate, String, Option<u64>, u64), usize>,
+    /// Whether there are changes that haven't been saved yet.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl CommandUsage {
+    pub fn load() -> Self {
+        let mut usage: CommandUsage = persist::load(FILE_NAME);
+        usage.prune();
+
+        usage
+    }
+
+    pub fn save(&mut self) {
+        self.prune();
+        persist::save_compact(FILE_NAME, self);
+
+        self.dirty = false;
+    }
+
+    /// Removes rows older than the retention period and rebuilds the index.
+    fn prune(&mut self) {
+        let cutoff = Utc::today().naive_utc() - Duration::days(RETENTION_DAYS);
+
+        self.rows.retain(|row| row.day >= cutoff);
+        self.index.clear();
+
+        for (i, row) in self.rows.iter().enumerate() {
+            let key = (row.day, row.command.clone(), row.guild_id, row.user_id);
+
+            self.index.insert(key, i);
+        }
+    }
+
+    pub fn record(&mut self, command: &str, guild_id: Option<u64>, user_id: u64) {
+        let day = Utc::today().naive_utc();
+        let key = (day, command.to_owned(), guild_id, user_id);
+
+        match self.index.get(&key) {
+            Some(&i) => self.rows[i].count += 1,
+            None => {
+                self.index.insert(key, self.rows.len());
+                self.rows.push(UsageRow {
+                    day: day,
+                    command: command.to_owned(),
+                    guild_id: guild_id,
+                    user_id: user_id,
+                    count: 1,
+                });
+            },
+        }
+
+        self.dirty = true;
+    }
+
+    /// Returns the rows that match the filter.
+    pub fn filtered(&self, filter: &UsageFilter) -> Vec<&UsageRow> {
+        self.rows.iter().filter(|row| filter.matches(row)).collect()
+    }
+
+    /// Sums the rows that match the filter by the given key, most used first.
+    pub fn summarize(&self, filter: &UsageFilter, by: GroupBy) -> Vec<(String, u64)> {
+        let mut totals = HashMap::new();
+
+        for row in self.filtered(filter) {
+            let key = match by {
+                GroupBy::Command => row.command.clone(),
+                GroupBy::Day => row.day.to_string(),
+                GroupBy::Guild => row.guild_id.map_or_else(|| "DMs".to_owned(), |id| id.to_string()),
+                GroupBy::User => row.user_id.to_string(),
+            };
+
+            *totals.entry(key).or_insert(0) += row.count;
+        }
+
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        totals
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use super::persist;
+
+const FILE_NAME: &'static str = "cooldowns.json";
+/// The longest a bucket's `per` can be, in seconds: a day.
+pub const MAX_PER: u64 = 86_400;
+
+/// What a bucket's uses are counted against.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    User,
+    Channel,
+    Guild,
+    Global,
+}
+
+impl Scope {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "user" => Some(Scope::User),
+            "channel" => Some(Scope::Channel),
+            "guild" => Some(Scope::Guild),
+            "global" => Some(Scope::Global),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Scope::User => "user",
+            Scope::Channel => "channel",
+            Scope::Guild => "guild",
+            Scope::Global => "global",
+        }
+    }
+}
+
+/// Allows a command to be used `uses` times every `per` seconds within a
+/// scope.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Bucket {
+    pub scope: Scope,
+    pub uses: usize,
+    pub per: u64,
+    /// Whether the bot's owners aren't limited by the bucket.
+    pub owner_bypass: bool,
+}
+
+impl Bucket {
+    fn new(scope: Scope, uses: usize, per: u64) -> Self {
+        Bucket {
+            scope: scope,
+            uses: uses,
+            per: per,
+            owner_bypass: true,
+        }
+    }
+}
+
+/// The bucket a command has unless it's been configured otherwise.
+///
+/// The network-backed commands are limited so that one user can't use up an
+/// API's quota, and `eval` so that compiles don't pile up; that one applies to
+/// owners too, since they're the only ones who can use it.
+pub fn default_bucket(command: &str) -> Option<Bucket> {
+    match command {
+        "anime" => Some(Bucket::new(Scope::User, 3, 30)),
+        "eval" => Some(Bucket {
+            owner_bypass: false,
+            ..Bucket::new(Scope::Global, 1, 10)
+        }),
+        "modping" => Some(Bucket::new(Scope::Guild, 1, 60)),
+        "udefined" => Some(Bucket::new(Scope::User, 3, 30)),
+        _ => None,
+    }
+}
+
+/// Why a command can't be used yet.
+pub struct Limited {
+    /// How long until it can be used again, in seconds.
+    pub retry_after: u64,
+    /// Whether the user should be told; they're only told once per cooldown
+    /// so that spamming a command doesn't turn into spamming replies.
+    pub notify: bool,
+}
+
+/// Per-command cooldown buckets and the recent uses counted against them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Cooldowns {
+    /// Buckets configured for commands, overriding their defaults. `None`
+    /// removes a command's default bucket.
+    pub buckets: HashMap<String, Option<Bucket>>,
+    /// When each command was recently used, by command and scope key.
+    #[serde(skip)]
+    uses: HashMap<(String, u64), VecDeque<Instant>>,
+    /// Until when users have already been told they're on cooldown, by
+    /// command and user.
+    #[serde(skip)]
+    notified: HashMap<(String, u64), Instant>,
+}
+
+impl Cooldowns {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+
+    /// The bucket the command is limited by, if any.
+    pub fn bucket(&self, command: &str) -> Option<Bucket> {
+        match self.buckets.get(command) {
+            Some(bucket) => *bucket,
+            None => default_bucket(command),
+        }
+    }
+
+    /// Counts a use of the command, unless its bucket is already full.
+    pub fn take(&mut self,
+                command: &str,
+                user_id: u64,
+                channel_id: u64,
+                guild_id: Option<u64>,
+                is_owner: bool)
+                -> Result<(), Limited> {
+        let bucket = match self.bucket(command) {
+            Some(bucket) => bucket,
+            None => return Ok(()),
+        };
+
+        if is_owner && bucket.owner_bypass {
+            return Ok(());
+        }
+
+        let key = match bucket.scope {
+            Scope::User => user_id,
+            Scope::Channel => channel_id,
+            // DMs don't have a guild, so they count as their own.
+            Scope::Guild => guild_id.unwrap_or(channel_id),
+            Scope::Global => 0,
+        };
+
+        // Saved buckets may predate the limit.
+        let per = Duration::from_secs(bucket.per.min(MAX_PER));
+        let now = Instant::now();
+        let uses = self.uses.entry((command.to_owned(), key)).or_insert_with(VecDeque::new);
+
+        while uses.front().map_or(false, |at| now.duration_since(*at) >= per) {
+            uses.pop_front();
+        }
+
+        if uses.len() < bucket.uses {
+            uses.push_back(now);
+
+            return Ok(());
+        }
+
+        let until = uses.front().and_then(|at| at.checked_add(per)).unwrap_or(now);
+        let remaining = until - now;
+        let retry_after = remaining.as_secs() + if remaining.subsec_nanos() > 0 { 1 } else { 0 };
+
+        let notified = self.notified.entry((command.to_owned(), user_id)).or_insert(now);
+        let notify = *notified <= now;
+        *notified = until;
+
+        Err(Limited {
+            retry_after: retry_after,
+            notify: notify,
+        })
+    }
+
+    /// Forgets uses and notices that no longer count towards anything.
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        let mut pers = HashMap::new();
+
+        for &(ref command, _) in self.uses.keys() {
+            if !pers.contains_key(command) {
+                pers.insert(command.clone(), self.bucket(command).map(|b| Duration::from_secs(b.per)));
+            }
+        }
+
+        self.uses.retain(|&(ref command, _), uses| {
+            match pers.get(command).and_then(|per| *per) {
+                Some(per) => uses.back().map_or(false, |at| now.duration_since(*at) < per),
+                None => false,
+            }
+        });
+        self.notified.retain(|_, until| *until > now);
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::Duration;
+
+/// The longest duration that's accepted, in seconds: a year.
+pub const MAX_SECONDS: i64 = 365 * 86_400;
+
+/// Parses a duration such as `90m`, `1d12h` or `2w`.
+///
+/// Accepted units are `w`, `d`, `h`, `m` and `s`. A bare number is taken as
+/// minutes. Durations longer than [`MAX_SECONDS`] aren't accepted.
+///
+/// [`MAX_SECONDS`]: constant.MAX_SECONDS.html
+pub fn parse(s: &str) -> Option<Duration> {
+    let total = match s.parse::<i64>() {
+        Ok(minutes) => minutes.checked_mul(60)?,
+        Err(_) => parse_units(s)?,
+    };
+
+    if total <= 0 || total > MAX_SECONDS {
+        return None;
+    }
+
+    Some(Duration::seconds(total))
+}
+
+/// Adds up the units of a duration such as `1d12h`, in seconds.
+fn parse_units(s: &str) -> Option<i64> {
+    let mut total = 0i64;
+    let mut number = String::new();
+
+    for c in s.chars() {
+        if c.is_digit(10) {
+            number.push(c);
+
+            continue;
+        }
+
+        let amount = match number.parse::<i64>() {
+            Ok(amount) => amount,
+            Err(_) => return None,
+        };
+        number.clear();
+
+        let unit = match c.to_ascii_lowercase() {
+            'w' => 604_800,
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+
+        total = total.checked_add(amount.checked_mul(unit)?)?;
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some(total)
+}
+
+/// Formats a duration for humans, such as `3d 4h 12m`.
+///
+/// Seconds are only shown for durations under a minute.
+pub fn format(duration: Duration) -> String {
+    let total = duration.num_seconds().max(0);
+
+    if total < 60 {
+        return format!("{}s", total);
+    }
+
+    let days = total / 86_400;
+    let hours = total % 86_400 / 3_600;
+    let minutes = total % 3_600 / 60;
+
+    let mut parts = vec![];
+
+    if days > 0 {
+        parts.push(format!("{}d", days));
+    }
+
+    if hours > 0 {
+        parts.push(format!("{}h", hours));
+    }
+
+    if minutes > 0 {
+        parts.push(format!("{}m", minutes));
+    }
+
+    parts.join(" ")
+}
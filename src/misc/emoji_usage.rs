@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use super::persist;
+
+const FILE_NAME: &'static str = "emoji_usage.json";
+
+lazy_static! {
+    static ref CUSTOM_EMOJI: Regex = Regex::new(r"<(a?):(\w+):(\d+)>").unwrap();
+}
+
+/// A custom emoji as written in a message.
+pub struct CustomEmoji {
+    pub animated: bool,
+    pub name: String,
+    pub id: u64,
+}
+
+/// Finds every custom emoji in the content.
+pub fn custom_emojis(content: &str) -> Vec<CustomEmoji> {
+    CUSTOM_EMOJI.captures_iter(content).filter_map(|caps| {
+        caps[3].parse().ok().map(|id| CustomEmoji {
+            animated: &caps[1] == "a",
+            name: caps[2].to_owned(),
+            id: id,
+        })
+    }).collect()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EmojiStat {
+    pub count: u64,
+    pub last_used: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GuildEmojiUsage {
+    /// When usage started being tracked in the guild.
+    pub since: DateTime<Utc>,
+    pub emojis: HashMap<u64, EmojiStat>,
+}
+
+/// How often each guild's custom emojis are used in messages and reactions.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct EmojiUsage {
+    pub guilds: HashMap<u64, GuildEmojiUsage>,
+    /// Whether there are changes that haven't been saved yet.
+    ///
+    /// Usage is recorded on every message, so saving is left to the scheduler.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl EmojiUsage {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&mut self) {
+        persist::save(FILE_NAME, self);
+
+        self.dirty = false;
+    }
+
+    pub fn record(&mut self, guild_id: u64, emoji_id: u64) {
+        let now = Utc::now();
+        let guild = self.guilds.entry(guild_id).or_insert_with(|| GuildEmojiUsage {
+            since: now,
+            emojis: HashMap::new(),
+        });
+        let stat = guild.emojis.entry(emoji_id).or_insert_with(|| EmojiStat {
+            count: 0,
+            last_used: now,
+        });
+
+        stat.count += 1;
+        stat.last_used = now;
+        self.dirty = true;
+    }
+}
@@ -0,0 +1,20 @@
+use std::collections::HashSet;
+use super::persist;
+
+const FILE_NAME: &'static str = "guild_blocklist.json";
+
+/// IDs of guilds that the bot leaves as soon as it receives them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct GuildBlocklist {
+    pub guilds: HashSet<u64>,
+}
+
+impl GuildBlocklist {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+}
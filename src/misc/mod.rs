@@ -1,3 +1,36 @@
+pub mod avatar;
+pub mod duration;
+pub mod permissions;
+pub mod persist;
+
+mod audit_log;
+mod boot_history;
+mod command_latency;
+mod command_toggles;
+mod command_usage;
+mod cooldowns;
+mod emoji_usage;
+mod guild_blocklist;
+mod presence;
+mod shard_status;
+mod snowflake;
+mod sticky_roles;
+mod temp_roles;
+mod unknown_events;
 mod uptime;
 
+pub use self::audit_log::{AuditEntry, AuditLog};
+pub use self::boot_history::{BootHistory, Session};
+pub use self::command_latency::{CommandLatency, LatencyStats};
+pub use self::command_toggles::{CommandToggles, ToggleRules};
+pub use self::command_usage::{CommandUsage, GroupBy, UsageFilter, UsageRow};
+pub use self::cooldowns::{default_bucket, Bucket, Cooldowns, Limited, Scope, MAX_PER};
+pub use self::emoji_usage::{custom_emojis, CustomEmoji, EmojiStat, EmojiUsage, GuildEmojiUsage};
+pub use self::guild_blocklist::GuildBlocklist;
+pub use self::presence::{Activity, ActivityKind, Presence};
+pub use self::shard_status::{format_latency, ShardStatus};
+pub use self::snowflake::Snowflake;
+pub use self::sticky_roles::{ListMode, StickyConfig, StickyMember, StickyRoles};
+pub use self::temp_roles::{TempRole, TempRoles};
+pub use self::unknown_events::UnknownEventStore;
 pub use self::uptime::Uptime;
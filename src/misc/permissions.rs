@@ -0,0 +1,234 @@
+use serenity::model::channel::{GuildChannel, PermissionOverwriteType};
+use serenity::model::guild::{Guild, Member};
+use serenity::model::id::{RoleId, UserId};
+use std::collections::HashMap;
+
+pub const ADMINISTRATOR: u64 = 1 << 3;
+pub const READ_MESSAGES: u64 = 1 << 10;
+
+/// Every permission Discord defines, by bit, with its display name.
+///
+/// This is kept apart from serenity's `Permissions` flags so that names can be
+/// looked up and listed without a method per permission.
+pub static PERMISSIONS: [(u64, &'static str); 29] = [
+    (1 << 0, "Create Invite"),
+    (1 << 1, "Kick Members"),
+    (1 << 2, "Ban Members"),
+    (1 << 3, "Administrator"),
+    (1 << 4, "Manage Channels"),
+    (1 << 5, "Manage Guild"),
+    (1 << 6, "Add Reactions"),
+    (1 << 7, "View Audit Log"),
+    (1 << 8, "Priority Speaker"),
+    (1 << 10, "Read Messages"),
+    (1 << 11, "Send Messages"),
+    (1 << 12, "Send TTS Messages"),
+    (1 << 13, "Manage Messages"),
+    (1 << 14, "Embed Links"),
+    (1 << 15, "Attach Files"),
+    (1 << 16, "Read Message History"),
+    (1 << 17, "Mention Everyone"),
+    (1 << 18, "Use External Emojis"),
+    (1 << 20, "Connect"),
+    (1 << 21, "Speak"),
+    (1 << 22, "Mute Members"),
+    (1 << 23, "Deafen Members"),
+    (1 << 24, "Move Members"),
+    (1 << 25, "Use VAD"),
+    (1 << 26, "Change Nickname"),
+    (1 << 27, "Manage Nicknames"),
+    (1 << 28, "Manage Roles"),
+    (1 << 29, "Manage Webhooks"),
+    (1 << 30, "Manage Emojis"),
+];
+
+/// Every permission bit set.
+pub fn all() -> u64 {
+    PERMISSIONS.iter().fold(0, |acc, &(bit, _)| acc | bit)
+}
+
+/// Lists the names of the permissions that are set.
+pub fn names(bits: u64) -> Vec<&'static str> {
+    PERMISSIONS.iter().filter(|&&(bit, _)| bits & bit != 0).map(|&(_, name)| name).collect()
+}
+
+/// The position of the member's highest role, or 0 if they only have
+/// @everyone or aren't cached.
+pub fn top_role_position(guild: &Guild, user_id: UserId) -> i64 {
+    guild.members
+        .get(&user_id)
+        .and_then(|member| member.roles
+            .iter()
+            .filter_map(|id| guild.roles.get(id))
+            .map(|role| role.position)
+            .max())
+        .unwrap_or(0)
+}
+
+/// Finds a permission's bit by name, ignoring case, spaces and underscores,
+/// so `Send Messages`, `send_messages` and `SENDMESSAGES` all match.
+pub fn parse(name: &str) -> Option<u64> {
+    let normalize = |s: &str| s.chars()
+        .filter(|c| *c != ' ' && *c != '_')
+        .collect::<String>()
+        .to_lowercase();
+    let name = normalize(name);
+
+    PERMISSIONS.iter().find(|&&(_, n)| normalize(n) == name).map(|&(bit, _)| bit)
+}
+
+/// A rule applied while computing permissions, in the order Discord applies
+/// them.
+pub struct Step {
+    pub description: String,
+    pub allow: u64,
+    pub deny: u64,
+    /// The permissions after this step was applied.
+    pub after: u64,
+}
+
+/// How a member's effective permissions were arrived at.
+pub struct Explanation {
+    pub steps: Vec<Step>,
+    pub value: u64,
+    /// The index of the step that last changed each permission bit.
+    deciders: HashMap<u64, usize>,
+}
+
+impl Explanation {
+    /// Computes the member's permissions in the guild, or in the channel if
+    /// one is given.
+    ///
+    /// A channel in a category has the category's overwrites applied before
+    /// its own.
+    pub fn compute(guild: &Guild, member: &Member, channel: Option<&GuildChannel>) -> Self {
+        let mut explanation = Explanation {
+            steps: vec![],
+            value: 0,
+            deciders: HashMap::new(),
+        };
+
+        let user_id = member.user.read().id;
+
+        if user_id == guild.owner_id {
+            explanation.apply("Server owner".to_owned(), all(), 0);
+
+            return explanation;
+        }
+
+        let everyone_id = RoleId(guild.id.0);
+
+        if let Some(role) = guild.roles.get(&everyone_id) {
+            explanation.apply("@everyone role".to_owned(), role.permissions.bits(), 0);
+        }
+
+        let mut roles = member.roles
+            .iter()
+            .filter_map(|id| guild.roles.get(id))
+            .collect::<Vec<_>>();
+        roles.sort_by(|a, b| b.position.cmp(&a.position));
+
+        for role in &roles {
+            explanation.apply(format!("Role {}", role.name), role.permissions.bits(), 0);
+        }
+
+        if explanation.value & ADMINISTRATOR != 0 {
+            explanation.apply("Administrator".to_owned(), all(), 0);
+
+            return explanation;
+        }
+
+        let channel = match channel {
+            Some(channel) => channel,
+            None => return explanation,
+        };
+
+        let category = channel.category_id.and_then(|id| guild.channels.get(&id));
+
+        if let Some(category) = category {
+            explanation.apply_overwrites(guild, member, &category.read());
+        }
+
+        explanation.apply_overwrites(guild, member, channel);
+
+        // Nothing else in a channel can be done without being able to see it.
+        if explanation.value & READ_MESSAGES == 0 {
+            explanation.apply(format!("Can't read #{}", channel.name), 0, all());
+        }
+
+        explanation
+    }
+
+    /// The step that last granted or denied the permission, if any did.
+    pub fn decider(&self, bit: u64) -> Option<&Step> {
+        self.deciders.get(&bit).map(|i| &self.steps[*i])
+    }
+
+    /// Applies a channel's overwrites for @everyone, then the member's roles,
+    /// then the member.
+    fn apply_overwrites(&mut self, guild: &Guild, member: &Member, channel: &GuildChannel) {
+        let everyone_id = RoleId(guild.id.0);
+        let user_id = member.user.read().id;
+
+        let mut everyone = (0, 0);
+        let mut role_overwrites = (0, 0);
+        let mut role_names = vec![];
+        let mut member_overwrite = (0, 0);
+
+        for overwrite in &channel.permission_overwrites {
+            let bits = (overwrite.allow.bits(), overwrite.deny.bits());
+
+            match overwrite.kind {
+                PermissionOverwriteType::Role(id) if id == everyone_id => everyone = bits,
+                PermissionOverwriteType::Role(id) if member.roles.contains(&id) => {
+                    role_overwrites.0 |= bits.0;
+                    role_overwrites.1 |= bits.1;
+
+                    if let Some(role) = guild.roles.get(&id) {
+                        role_names.push(role.name.clone());
+                    }
+                },
+                PermissionOverwriteType::Member(id) if id == user_id => member_overwrite = bits,
+                _ => {},
+            }
+        }
+
+        self.apply(format!("#{} overwrite for @everyone", channel.name),
+                   everyone.0,
+                   everyone.1);
+
+        if !role_names.is_empty() {
+            self.apply(format!("#{} overwrites for {}", channel.name, role_names.join(", ")),
+                       role_overwrites.0,
+                       role_overwrites.1);
+        }
+
+        self.apply(format!("#{} overwrite for the member", channel.name),
+                   member_overwrite.0,
+                   member_overwrite.1);
+    }
+
+    fn apply(&mut self, description: String, allow: u64, deny: u64) {
+        let before = self.value;
+        let denied = before & !deny;
+        let after = denied | allow;
+        let index = self.steps.len();
+
+        for &(bit, _) in PERMISSIONS.iter() {
+            let was_denied = before & bit != 0 && deny & bit != 0;
+            let was_granted = denied & bit == 0 && allow & bit != 0;
+
+            if was_denied || was_granted {
+                self.deciders.insert(bit, index);
+            }
+        }
+
+        self.value = after;
+        self.steps.push(Step {
+            description: description,
+            allow: allow,
+            deny: deny,
+            after: after,
+        });
+    }
+}
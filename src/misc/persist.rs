@@ -0,0 +1,84 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Returns the directory that persisted state is stored in.
+///
+/// This is `DATA_DIR` if set, falling back to `./data`.
+pub fn data_dir() -> PathBuf {
+    env::var("DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("./data"))
+}
+
+/// Loads the JSON file named `name` from the data directory.
+///
+/// Falls back to the default value if the file does not exist or can not be
+/// parsed, so a corrupt file never prevents the bot from starting.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    let path = data_dir().join(name);
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => return T::default(),
+    };
+
+    match serde_json::from_reader(file) {
+        Ok(value) => value,
+        Err(why) => {
+            warn!("Err parsing {}: {:?}", path.display(), why);
+
+            T::default()
+        },
+    }
+}
+
+/// Saves `value` as JSON to the file named `name` in the data directory.
+///
+/// The value is written to a temporary file first and then renamed over the
+/// old one, so a crash mid-write can not leave a truncated file behind.
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    write(name, serde_json::to_vec_pretty(value));
+}
+
+/// Saves `value` like [`save`], but without whitespace, for files that grow
+/// large and aren't meant to be read by hand.
+///
+/// [`save`]: fn.save.html
+pub fn save_compact<T: Serialize>(name: &str, value: &T) {
+    write(name, serde_json::to_vec(value));
+}
+
+fn write(name: &str, bytes: serde_json::Result<Vec<u8>>) {
+    let dir = data_dir();
+
+    if let Err(why) = fs::create_dir_all(&dir) {
+        warn!("Err creating {}: {:?}", dir.display(), why);
+
+        return;
+    }
+
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(why) => {
+            warn!("Err serializing {}: {:?}", name, why);
+
+            return;
+        },
+    };
+
+    let path = dir.join(name);
+    let tmp = dir.join(format!("{}.tmp", name));
+
+    let res = File::create(&tmp)
+        .and_then(|mut f| f.write_all(&bytes))
+        .and_then(|_| fs::rename(&tmp, &path));
+
+    if let Err(why) = res {
+        warn!("Err saving {}: {:?}", path.display(), why);
+    }
+}
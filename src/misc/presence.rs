@@ -0,0 +1,139 @@
+use serenity::model::gateway::Game;
+use serenity::model::user::OnlineStatus;
+use std::time::{Duration, Instant};
+use super::persist;
+
+const FILE_NAME: &'static str = "presence.json";
+/// The default time between rotating activities, in seconds.
+const DEFAULT_INTERVAL: u64 = 300;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Listening,
+    Playing,
+    Streaming,
+}
+
+impl ActivityKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match &s.to_lowercase()[..] {
+            "listening" => Some(ActivityKind::Listening),
+            "playing" => Some(ActivityKind::Playing),
+            "streaming" => Some(ActivityKind::Streaming),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ActivityKind::Listening => "Listening to",
+            ActivityKind::Playing => "Playing",
+            ActivityKind::Streaming => "Streaming",
+        }
+    }
+}
+
+/// An activity shown in the bot's presence.
+///
+/// The text can contain `{guilds}`, `{shard}` and `{shards}`, which are filled
+/// in for each shard when the presence is set.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Activity {
+    pub kind: ActivityKind,
+    pub text: String,
+    /// The stream URL, for streaming activities.
+    pub url: Option<String>,
+}
+
+impl Activity {
+    pub fn render(&self, shard_id: u64, shard_count: u64, guilds: usize) -> Game {
+        let text = self.text
+            .replace("{guilds}", &guilds.to_string())
+            .replace("{shard}", &(shard_id + 1).to_string())
+            .replace("{shards}", &shard_count.to_string());
+
+        match (self.kind, self.url.as_ref()) {
+            (ActivityKind::Listening, _) => Game::listening(&text),
+            (ActivityKind::Streaming, Some(url)) => Game::streaming(&text, url),
+            _ => Game::playing(&text),
+        }
+    }
+}
+
+/// The configured presence: an online status and the activities that are
+/// rotated between.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Presence {
+    pub status: OnlineStatus,
+    pub activities: Vec<Activity>,
+    /// How long each activity is shown for when rotating, in seconds.
+    pub interval: u64,
+    /// The index of the activity being shown.
+    #[serde(skip)]
+    current: usize,
+    #[serde(skip)]
+    last_rotated: Option<Instant>,
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Presence {
+            status: OnlineStatus::Online,
+            activities: vec![Activity {
+                kind: ActivityKind::Playing,
+                text: "nano help [{shard}/{shards}]".to_owned(),
+                url: None,
+            }],
+            interval: DEFAULT_INTERVAL,
+            current: 0,
+            last_rotated: None,
+        }
+    }
+}
+
+impl Presence {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+
+    /// The activity being shown, if there are any.
+    pub fn current(&self) -> Option<&Activity> {
+        self.activities.get(self.current % self.activities.len().max(1))
+    }
+
+    /// Moves on to the next activity if the current one has been shown for
+    /// long enough, returning whether it changed.
+    pub fn rotate(&mut self) -> bool {
+        if self.activities.len() < 2 {
+            return false;
+        }
+
+        let interval = Duration::from_secs(self.interval);
+
+        match self.last_rotated {
+            Some(at) if at.elapsed() < interval => false,
+            Some(_) => {
+                self.current = (self.current + 1) % self.activities.len();
+                self.last_rotated = Some(Instant::now());
+
+                true
+            },
+            None => {
+                self.last_rotated = Some(Instant::now());
+
+                false
+            },
+        }
+    }
+
+    /// Starts showing the activities from the first one.
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.last_rotated = Some(Instant::now());
+    }
+}
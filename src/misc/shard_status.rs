@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Heartbeat health of a shard, as last observed from the shard manager.
+#[derive(Clone, Debug, Default)]
+pub struct ShardStatus {
+    pub latency: Option<Duration>,
+    /// When a heartbeat ACK was last seen.
+    ///
+    /// The shard manager only exposes the latest latency, so this is when the
+    /// latency was last seen to change, which is within one check of the ACK.
+    pub last_ack: Option<DateTime<Utc>>,
+    /// How many consecutive checks the latency has been over the warning
+    /// threshold.
+    pub slow_checks: u32,
+    /// Whether the owner has been warned about the current slow period.
+    pub warned: bool,
+}
+
+impl ShardStatus {
+    pub fn observe(&mut self, latency: Option<Duration>) {
+        if latency.is_some() && latency != self.latency {
+            self.last_ack = Some(Utc::now());
+        }
+
+        self.latency = latency;
+    }
+}
+
+/// Formats a latency in milliseconds, or `N/A` if there isn't one yet.
+pub fn format_latency(latency: Option<Duration>) -> String {
+    match latency {
+        Some(latency) => {
+            let ms = latency.as_secs() * 1000 + u64::from(latency.subsec_nanos()) / 1_000_000;
+
+            format!("{}ms", ms)
+        },
+        None => "N/A".to_owned(),
+    }
+}
@@ -0,0 +1,27 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// Milliseconds between the Unix epoch and Discord's epoch, the first second
+/// of 2015.
+const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+/// The parts that a Discord ID is made up of.
+pub struct Snowflake {
+    pub timestamp: DateTime<Utc>,
+    pub worker_id: u64,
+    pub process_id: u64,
+    pub increment: u64,
+}
+
+impl Snowflake {
+    pub fn decode(id: u64) -> Self {
+        let ms = (id >> 22) + DISCORD_EPOCH;
+        let naive = NaiveDateTime::from_timestamp((ms / 1000) as i64, (ms % 1000) as u32 * 1_000_000);
+
+        Snowflake {
+            timestamp: DateTime::from_utc(naive, Utc),
+            worker_id: (id >> 17) & 0x1F,
+            process_id: (id >> 12) & 0x1F,
+            increment: id & 0xFFF,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use super::persist;
+
+const FILE_NAME: &'static str = "sticky_roles.json";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ListMode {
+    /// Only the listed roles are sticky.
+    Allow,
+    /// Every role except the listed ones is sticky.
+    Deny,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StickyConfig {
+    pub enabled: bool,
+    /// Whether the member's nickname is restored along with their roles.
+    pub nickname: bool,
+    /// How long after leaving a member's roles are kept, in seconds.
+    pub period: i64,
+    pub mode: ListMode,
+    pub roles: HashSet<u64>,
+}
+
+impl StickyConfig {
+    pub fn is_sticky(&self, role_id: u64) -> bool {
+        match self.mode {
+            ListMode::Allow => self.roles.contains(&role_id),
+            ListMode::Deny => !self.roles.contains(&role_id),
+        }
+    }
+}
+
+impl Default for StickyConfig {
+    fn default() -> Self {
+        StickyConfig {
+            enabled: false,
+            nickname: false,
+            period: Duration::days(30).num_seconds(),
+            mode: ListMode::Deny,
+            roles: HashSet::new(),
+        }
+    }
+}
+
+/// The state of a member when they left a guild.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StickyMember {
+    pub left_at: DateTime<Utc>,
+    pub nick: Option<String>,
+    pub roles: Vec<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct StickyGuild {
+    pub config: StickyConfig,
+    pub members: HashMap<u64, StickyMember>,
+}
+
+impl StickyGuild {
+    /// Removes members that left longer ago than the configured period.
+    pub fn prune(&mut self) {
+        let cutoff = Utc::now() - Duration::seconds(self.config.period);
+
+        self.members.retain(|_, member| member.left_at > cutoff);
+    }
+}
+
+/// Roles, and optionally nicknames, of members who left a guild, so they can
+/// be restored if they rejoin.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct StickyRoles {
+    pub guilds: HashMap<u64, StickyGuild>,
+}
+
+impl StickyRoles {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+
+    /// Records a departing member's sticky roles and nickname, if the guild
+    /// has sticky roles enabled.
+    ///
+    /// Returns whether anything was recorded.
+    pub fn record(&mut self, guild_id: u64, user_id: u64, roles: &[u64], nick: Option<String>)
+        -> bool {
+        let guild = match self.guilds.get_mut(&guild_id) {
+            Some(guild) if guild.config.enabled => guild,
+            _ => return false,
+        };
+
+        guild.prune();
+
+        let roles = roles.iter()
+            .cloned()
+            .filter(|id| guild.config.is_sticky(*id))
+            .collect::<Vec<_>>();
+        let nick = if guild.config.nickname { nick } else { None };
+
+        if roles.is_empty() && nick.is_none() {
+            return false;
+        }
+
+        guild.members.insert(user_id, StickyMember {
+            left_at: Utc::now(),
+            nick: nick,
+            roles: roles,
+        });
+
+        true
+    }
+
+    /// Returns a rejoining member's saved state, if they left within the
+    /// guild's configured period.
+    ///
+    /// The state is kept until it's [`remove`]d, so that a failed restore can
+    /// be retried on the member's next join.
+    ///
+    /// [`remove`]: #method.remove
+    pub fn get(&mut self, guild_id: u64, user_id: u64) -> Option<StickyMember> {
+        let guild = match self.guilds.get_mut(&guild_id) {
+            Some(guild) if guild.config.enabled => guild,
+            _ => return None,
+        };
+
+        guild.prune();
+        guild.members.get(&user_id).cloned()
+    }
+
+    /// Forgets a member's saved state once it's been restored.
+    pub fn remove(&mut self, guild_id: u64, user_id: u64) -> bool {
+        self.guilds
+            .get_mut(&guild_id)
+            .map_or(false, |guild| guild.members.remove(&user_id).is_some())
+    }
+}
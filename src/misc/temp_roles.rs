@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use super::persist;
+
+const FILE_NAME: &'static str = "temp_roles.json";
+
+/// A role given to a member that is to be removed at `expires_at`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TempRole {
+    pub guild_id: u64,
+    pub user_id: u64,
+    pub role_id: u64,
+    pub granted_by: u64,
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TempRoles {
+    pub roles: Vec<TempRole>,
+}
+
+impl TempRoles {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        persist::save(FILE_NAME, self);
+    }
+
+    /// Adds a temporary role, replacing the expiry of an existing one for the
+    /// same member and role.
+    pub fn insert(&mut self, temp_role: TempRole) {
+        self.remove(&temp_role);
+        self.roles.push(temp_role);
+    }
+
+    /// Whether the temporary role is still stored with the same expiry, i.e.
+    /// it hasn't been re-granted or extended since it was looked up.
+    pub fn is_current(&self, temp_role: &TempRole) -> bool {
+        self.roles.iter().any(|r| is_same(r, temp_role) && r.expires_at == temp_role.expires_at)
+    }
+
+    /// Whether the member has the role temporarily.
+    pub fn contains(&self, guild_id: u64, user_id: u64, role_id: u64) -> bool {
+        self.roles.iter().any(|r| {
+            r.guild_id == guild_id && r.user_id == user_id && r.role_id == role_id
+        })
+    }
+
+    /// Returns the temporary roles that have expired as of `now`.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<TempRole> {
+        self.roles.iter().filter(|r| r.expires_at <= now).cloned().collect()
+    }
+
+    pub fn remove(&mut self, temp_role: &TempRole) {
+        self.roles.retain(|r| !is_same(r, temp_role));
+    }
+
+    /// Removes the temporary role only if its expiry hasn't changed since it
+    /// was looked up.
+    ///
+    /// Returns whether it was removed.
+    pub fn remove_expired(&mut self, temp_role: &TempRole) -> bool {
+        let before = self.roles.len();
+
+        self.roles.retain(|r| !is_same(r, temp_role) || r.expires_at != temp_role.expires_at);
+
+        self.roles.len() != before
+    }
+
+    /// Returns the active temporary roles in a guild, soonest to expire first.
+    pub fn in_guild(&self, guild_id: u64) -> Vec<&TempRole> {
+        let mut roles = self.roles.iter().filter(|r| r.guild_id == guild_id).collect::<Vec<_>>();
+        roles.sort_by_key(|r| r.expires_at);
+
+        roles
+    }
+}
+
+/// Whether two temporary roles are for the same member and role.
+fn is_same(a: &TempRole, b: &TempRole) -> bool {
+    a.guild_id == b.guild_id && a.user_id == b.user_id && a.role_id == b.role_id
+}
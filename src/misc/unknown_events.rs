@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use super::persist;
+
+/// The name of the file the store is persisted to.
+const FILE_NAME: &'static str = "unknown_events.json";
+/// The maximum number of distinct event names kept.
+const MAX_EVENTS: usize = 100;
+/// The maximum number of sample payloads kept per event; older samples are
+/// dropped first.
+const MAX_SAMPLES: usize = 5;
+/// Payloads larger than this, in bytes of serialized JSON, are counted but
+/// not sampled.
+const MAX_SAMPLE_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UnknownEvent {
+    pub count: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub samples: VecDeque<Value>,
+}
+
+/// A capped on-disk record of gateway events that serenity did not know how
+/// to deserialize.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UnknownEventStore {
+    pub events: BTreeMap<String, UnknownEvent>,
+    /// Whether there are changes that haven't been saved yet.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl UnknownEventStore {
+    pub fn load() -> Self {
+        persist::load(FILE_NAME)
+    }
+
+    pub fn save(&mut self) {
+        persist::save(FILE_NAME, self);
+
+        self.dirty = false;
+    }
+
+    /// Records an occurrence of the event, keeping the payload as a sample
+    /// if it isn't too large.
+    ///
+    /// Returns `false` if the event is new and the store is already full.
+    pub fn record(&mut self, name: String, value: Value) -> bool {
+        if !self.events.contains_key(&name) && self.events.len() >= MAX_EVENTS {
+            return false;
+        }
+
+        let now = Utc::now();
+        let event = self.events.entry(name).or_insert_with(|| UnknownEvent {
+            count: 0,
+            first_seen: now,
+            last_seen: now,
+            samples: VecDeque::new(),
+        });
+
+        event.count += 1;
+        event.last_seen = now;
+
+        let size = value.to_string().len();
+
+        if size <= MAX_SAMPLE_SIZE {
+            if event.samples.len() >= MAX_SAMPLES {
+                event.samples.pop_front();
+            }
+
+            event.samples.push_back(value);
+        }
+
+        self.dirty = true;
+
+        true
+    }
+}
@@ -25,11 +25,29 @@ pub struct Uptime {
     /// probably _technically_ be an Option, _but_ a user will never be able to
     /// request the uptime if there is no connection, so it's okay.
     pub connection: DateTime<Utc>,
+    /// Number of times the shard has reconnected, either with a new session
+    /// or by resuming the old one.
+    pub reconnects: u64,
+    /// When the shard last resumed a session.
+    pub last_resume: Option<DateTime<Utc>>,
+    /// Number of sessions the shard has started.
+    sessions: u64,
 }
 
 impl Uptime {
     pub fn connect(&mut self) {
         self.connection = Utc::now();
+
+        if self.sessions > 0 {
+            self.reconnects += 1;
+        }
+
+        self.sessions += 1;
+    }
+
+    pub fn resume(&mut self) {
+        self.last_resume = Some(Utc::now());
+        self.reconnects += 1;
     }
 }
 
@@ -40,6 +58,9 @@ impl Default for Uptime {
         Uptime {
             boot: now,
             connection: now,
+            reconnects: 0,
+            last_resume: None,
+            sessions: 0,
         }
     }
 }
@@ -1,5 +1,6 @@
 pub use serenity::client::Context;
 pub use serenity::framework::standard::{Args, Command, CommandError};
 pub use serenity::model::channel::Message;
+pub use ::error::Error;
 
 pub type CommandResult = Result<(), CommandError>;
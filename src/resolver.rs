@@ -0,0 +1,281 @@
+//! Turns command arguments into users, members, roles and channels.
+//!
+//! Every resolver accepts a mention, a raw ID or a name, trying in order:
+//! exact names, case-insensitive names, case-insensitive prefixes and then
+//! case-insensitive substrings. The first of those that matches anything
+//! wins, and if it matches more than one thing the result is ambiguous.
+
+use serenity::model::channel::GuildChannel;
+use serenity::model::guild::{Guild, Member, Role};
+use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
+use serenity::model::user::User;
+use std::fmt::Write;
+use ::error::Error;
+
+/// The most candidates listed when asking the invoker to pick.
+const MAX_CANDIDATES: usize = 10;
+
+pub enum Resolved<T> {
+    Found(T),
+    Ambiguous(Vec<T>),
+    NotFound,
+}
+
+impl<T> Resolved<T> {
+    /// Returns the resolved value, otherwise an error saying that nothing was
+    /// found or listing the candidates for the invoker to pick from.
+    pub fn or_error<F>(self, kind: &str, describe: F) -> Result<T, Error>
+        where F: Fn(&T) -> String {
+        match self {
+            Resolved::Found(value) => Ok(value),
+            Resolved::Ambiguous(candidates) => {
+                let mut s = format!("Multiple {}s matched; which did you mean?\n", kind);
+
+                for (i, candidate) in candidates.iter().take(MAX_CANDIDATES).enumerate() {
+                    let _ = write!(s, "{}. {}\n", i + 1, describe(candidate));
+                }
+
+                if candidates.len() > MAX_CANDIDATES {
+                    let _ = write!(s, "...and {} more\n", candidates.len() - MAX_CANDIDATES);
+                }
+
+                s.push_str("Use a mention or ID to pick one.");
+
+                Err(Error::BadArgument(s))
+            },
+            Resolved::NotFound => Err(Error::NotFound(kind.to_owned())),
+        }
+    }
+
+    fn map<U, F: Fn(T) -> U>(self, f: F) -> Resolved<U> {
+        match self {
+            Resolved::Found(value) => Resolved::Found(f(value)),
+            Resolved::Ambiguous(values) => Resolved::Ambiguous(values.into_iter().map(f).collect()),
+            Resolved::NotFound => Resolved::NotFound,
+        }
+    }
+}
+
+/// Resolves a channel in the guild from `<#id>`, an ID, or a name with or
+/// without a leading `#`.
+///
+/// Only channels that `viewer` can read are considered, so that hidden
+/// channels aren't revealed.
+pub fn channel(guild: &Guild, viewer: UserId, arg: &str) -> Resolved<GuildChannel> {
+    let arg = arg.trim();
+    let visible = |id: ChannelId| guild.permissions_in(id, viewer).read_messages();
+
+    if let Some(id) = parse_id(arg, "<#", ">") {
+        if let Some(channel) = guild.channels.get(&ChannelId(id)) {
+            if visible(ChannelId(id)) {
+                return Resolved::Found(channel.read().clone());
+            }
+        }
+    }
+
+    let channels = guild.channels
+        .iter()
+        .filter(|&(id, _)| visible(*id))
+        .map(|(_, c)| c.read().clone())
+        .collect::<Vec<_>>();
+    let name = arg.trim_left_matches('#');
+
+    by_name(channels, name, |c| vec![c.name.clone()])
+}
+
+/// Resolves a member of the guild from `<@id>`, `<@!id>`, an ID,
+/// `name#discrim`, a nickname or a username.
+///
+/// Members given by ID that aren't cached are retrieved over REST, after the
+/// cache is no longer locked.
+pub fn member(guild_id: GuildId, arg: &str) -> Resolved<Member> {
+    let resolved = match guild_id.find() {
+        Some(guild) => {
+            let guild = guild.read();
+
+            cached_member(&guild, arg)
+        },
+        None => Resolved::NotFound,
+    };
+
+    match (resolved, parse_user_id(arg.trim())) {
+        (Resolved::NotFound, Some(id)) => guild_id.member(id).map_or(Resolved::NotFound, Resolved::Found),
+        (resolved, _) => resolved,
+    }
+}
+
+/// Resolves a member like `member` does, but only from the cache.
+pub fn cached_member(guild: &Guild, arg: &str) -> Resolved<Member> {
+    let arg = arg.trim();
+
+    if let Some(id) = parse_user_id(arg) {
+        if let Some(member) = guild.members.get(&UserId(id)) {
+            return Resolved::Found(member.clone());
+        }
+    }
+
+    if let Some(pos) = arg.rfind('#') {
+        if let Ok(discriminator) = arg[pos + 1..].parse::<u16>() {
+            let name = &arg[..pos];
+            let found = guild.members.values().find(|m| {
+                let user = m.user.read();
+
+                user.name == name && user.discriminator == discriminator
+            });
+
+            if let Some(member) = found {
+                return Resolved::Found(member.clone());
+            }
+        }
+    }
+
+    let members = guild.members.values().collect::<Vec<_>>();
+
+    by_name(members, arg, |m| {
+        let mut names = vec![m.user.read().name.clone()];
+        names.extend(m.nick.clone());
+
+        names
+    }).map(|m| m.clone())
+}
+
+/// Resolves a role in the guild from `<@&id>`, an ID or a name.
+pub fn role(guild: &Guild, arg: &str) -> Resolved<Role> {
+    let arg = arg.trim();
+
+    if let Some(id) = parse_id(arg, "<@&", ">") {
+        if let Some(role) = guild.roles.get(&RoleId(id)) {
+            return Resolved::Found(role.clone());
+        }
+    }
+
+    let roles = guild.roles.values().collect::<Vec<_>>();
+
+    by_name(roles, arg, |r| vec![r.name.clone()]).map(|r| r.clone())
+}
+
+/// Resolves a user like `member` does when in a guild.
+///
+/// Outside of a guild, or for users given by ID that aren't in it, the user
+/// is retrieved over REST, after the cache is no longer locked.
+pub fn user(guild_id: Option<GuildId>, arg: &str) -> Resolved<User> {
+    let resolved = match guild_id.and_then(|id| id.find()) {
+        Some(guild) => {
+            let guild = guild.read();
+
+            cached_member(&guild, arg).map(|m| m.user.read().clone())
+        },
+        None => Resolved::NotFound,
+    };
+
+    match (resolved, parse_user_id(arg.trim())) {
+        (Resolved::NotFound, Some(id)) => UserId(id).get().map_or(Resolved::NotFound, Resolved::Found),
+        (resolved, _) => resolved,
+    }
+}
+
+/// Describes a user for a list of candidates.
+pub fn describe_user(user: &User) -> String {
+    format!("{} ({})", user.tag(), user.id)
+}
+
+/// Describes a member for a list of candidates.
+pub fn describe_member(member: &Member) -> String {
+    let user = member.user.read();
+
+    match member.nick {
+        Some(ref nick) => format!("{} aka {} ({})", user.tag(), nick, user.id),
+        None => format!("{} ({})", user.tag(), user.id),
+    }
+}
+
+/// Describes a role for a list of candidates.
+pub fn describe_role(role: &Role) -> String {
+    format!("{} ({})", role.name, role.id)
+}
+
+/// Describes a channel for a list of candidates.
+pub fn describe_channel(channel: &GuildChannel) -> String {
+    format!("#{} ({})", channel.name, channel.id)
+}
+
+fn by_name<T, F>(items: Vec<T>, arg: &str, names: F) -> Resolved<T>
+    where F: Fn(&T) -> Vec<String> {
+    if arg.is_empty() {
+        return Resolved::NotFound;
+    }
+
+    let lower = arg.to_lowercase();
+    let matchers: [&Fn(&str) -> bool; 4] = [
+        &|name| name == arg,
+        &|name| name.to_lowercase() == lower,
+        &|name| name.to_lowercase().starts_with(&lower),
+        &|name| name.to_lowercase().contains(&lower),
+    ];
+
+    let mut items = items.into_iter().map(|item| (names(&item), item)).collect::<Vec<_>>();
+
+    for matcher in matchers.iter() {
+        let (mut found, rest): (Vec<_>, Vec<_>) = items.into_iter()
+            .partition(|&(ref names, _)| names.iter().any(|name| matcher(name)));
+
+        match found.len() {
+            0 => items = rest,
+            1 => return Resolved::Found(found.remove(0).1),
+            _ => return Resolved::Ambiguous(found.into_iter().map(|(_, item)| item).collect()),
+        }
+    }
+
+    Resolved::NotFound
+}
+
+/// Parses either a raw ID or one wrapped in the given mention syntax.
+fn parse_id(arg: &str, prefix: &str, suffix: &str) -> Option<u64> {
+    if arg.starts_with(prefix) && arg.ends_with(suffix) {
+        return arg[prefix.len()..arg.len() - suffix.len()].parse().ok();
+    }
+
+    arg.parse().ok()
+}
+
+/// Parses a user ID from `<@id>`, `<@!id>` or a raw ID.
+pub fn parse_user_id(arg: &str) -> Option<u64> {
+    parse_id(arg, "<@!", ">").or_else(|| parse_id(arg, "<@", ">"))
+}
+
+/// Parses an ID from a raw ID, a user, role or channel mention, or a custom
+/// emoji, rejecting anything else.
+pub fn parse_snowflake(arg: &str) -> Option<u64> {
+    let inner = if arg.starts_with('<') && arg.ends_with('>') {
+        &arg[1..arg.len() - 1]
+    } else {
+        return parse_digits(arg);
+    };
+
+    for prefix in &["@!", "@&", "@", "#"] {
+        if inner.starts_with(prefix) {
+            return parse_digits(&inner[prefix.len()..]);
+        }
+    }
+
+    // Custom emojis are `<:name:id>`, or `<a:name:id>` when animated.
+    let emoji = if inner.starts_with("a:") { &inner[1..] } else { inner };
+    let mut parts = emoji.splitn(3, ':');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(""), Some(name), Some(id)) if is_emoji_name(name) => parse_digits(id),
+        _ => None,
+    }
+}
+
+fn parse_digits(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.chars().all(|c| c.is_digit(10)) {
+        return None;
+    }
+
+    s.parse().ok()
+}
+
+fn is_emoji_name(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -0,0 +1,258 @@
+use chrono::{Duration, Utc};
+use serenity::CACHE;
+use serenity::model::id::{GuildId, RoleId, UserId};
+use serenity::prelude::Mutex;
+use std::env;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration as StdDuration;
+use typemap::ShareMap;
+use ::actions;
+use ::misc::format_latency;
+use ::store::{
+    AuditLogStore,
+    BootHistoryStore,
+    CommandCounter,
+    CommandTimings,
+    CooldownStore,
+    EmojiUsageStore,
+    PresenceStore,
+    ShardMessengers,
+    ShardHealth,
+    ShardManagerContainer,
+    TempRoleStore,
+    UnknownEvents,
+};
+
+/// How often due jobs are checked for, in seconds.
+const TICK: u64 = 30;
+/// How long after expiry removing a temporary role is retried before giving
+/// up, in hours.
+const GIVE_UP_AFTER: i64 = 24;
+/// The default heartbeat latency over which a shard is considered slow, in
+/// milliseconds. Overridden by the `LATENCY_WARN_MS` env var.
+const LATENCY_WARN_MS: u64 = 1000;
+/// How many consecutive slow checks it takes to warn the owner.
+const SLOW_CHECKS_TO_WARN: u32 = 10;
+
+/// Starts the thread that runs persisted jobs once they're due.
+///
+/// The first check happens immediately, so jobs that came due while the bot
+/// was down are caught up on boot.
+pub fn start(data: Arc<Mutex<ShareMap>>) {
+    let res = thread::Builder::new()
+        .name("scheduler".to_owned())
+        .spawn(move || loop {
+            check_latency(&data);
+            expire_temp_roles(&data);
+            touch_boot_history(&data);
+            save_emoji_usage(&data);
+            save_command_usage(&data);
+            save_unknown_events(&data);
+            rotate_presence(&data);
+            prune_cooldowns(&data);
+            prune_command_timings(&data);
+
+            thread::sleep(StdDuration::from_secs(TICK));
+        });
+
+    if let Err(why) = res {
+        error!("Err starting scheduler: {:?}", why);
+    }
+}
+
+/// Records each shard's heartbeat latency, warning the owner once a shard has
+/// been slow for `SLOW_CHECKS_TO_WARN` checks in a row.
+fn check_latency(data: &Mutex<ShareMap>) {
+    let threshold = env::var("LATENCY_WARN_MS")
+        .ok()
+        .and_then(|x| x.parse::<u64>().ok())
+        .unwrap_or(LATENCY_WARN_MS);
+
+    let manager = match data.lock().get::<ShardManagerContainer>() {
+        Some(manager) => Arc::clone(manager),
+        None => return,
+    };
+
+    let latencies = {
+        let manager = manager.lock();
+        let runners = manager.runners.lock();
+
+        runners.iter().map(|(id, runner)| (id.0, runner.latency)).collect::<Vec<_>>()
+    };
+
+    let mut warnings = vec![];
+
+    {
+        let mut data = data.lock();
+        let health = data.get_mut::<ShardHealth>().unwrap();
+
+        for (shard_id, latency) in latencies {
+            let status = health.entry(shard_id).or_insert_with(Default::default);
+            status.observe(latency);
+
+            let slow = latency.map_or(false, |latency| {
+                latency.as_secs() * 1000 + u64::from(latency.subsec_nanos()) / 1_000_000 > threshold
+            });
+
+            if !slow {
+                status.slow_checks = 0;
+                status.warned = false;
+
+                continue;
+            }
+
+            status.slow_checks += 1;
+
+            if status.slow_checks >= SLOW_CHECKS_TO_WARN && !status.warned {
+                status.warned = true;
+
+                warnings.push(format!("Shard {} has had a heartbeat latency over {}ms for {} checks; currently {}",
+                                      shard_id,
+                                      threshold,
+                                      status.slow_checks,
+                                      format_latency(latency)));
+            }
+        }
+    }
+
+    for warning in warnings {
+        warn!("{}", warning);
+
+        actions::notify_owner(data, "HEALTH_LOG_CHANNEL_ID", &warning, actions::DryRunPolicy::Send);
+    }
+}
+
+/// Sets the configured presence on every shard, or only on the given one.
+pub fn update_presence(data: &Mutex<ShareMap>, only: Option<u64>) {
+    let (guilds, shard_count) = {
+        let cache = CACHE.read();
+
+        (cache.guilds.len(), cache.shard_count)
+    };
+
+    let updates = {
+        let data = data.lock();
+        let presence = data.get::<PresenceStore>().unwrap();
+        let messengers = data.get::<ShardMessengers>().unwrap();
+
+        messengers.iter()
+            .filter(|&(id, _)| only.map_or(true, |only| only == *id))
+            .map(|(id, messenger)| {
+                let game = presence.current().map(|a| a.render(*id, shard_count, guilds));
+
+                (*id, messenger.clone(), game, presence.status)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for (shard_id, messenger, game, status) in updates {
+        actions::set_presence(data, shard_id, &messenger, game, status);
+    }
+}
+
+fn prune_command_timings(data: &Mutex<ShareMap>) {
+    data.lock().get_mut::<CommandTimings>().unwrap().prune();
+}
+
+fn prune_cooldowns(data: &Mutex<ShareMap>) {
+    data.lock().get_mut::<CooldownStore>().unwrap().prune();
+}
+
+fn rotate_presence(data: &Mutex<ShareMap>) {
+    // Otherwise every rotation would be reported, flooding the dry-run log
+    // with the same few activities.
+    if actions::is_dry(data, None) {
+        return;
+    }
+
+    let rotated = data.lock().get_mut::<PresenceStore>().unwrap().rotate();
+
+    if rotated {
+        update_presence(data, None);
+    }
+}
+
+fn save_command_usage(data: &Mutex<ShareMap>) {
+    let mut data = data.lock();
+    let usage = data.get_mut::<CommandCounter>().unwrap();
+
+    if usage.dirty {
+        usage.save();
+    }
+}
+
+fn save_unknown_events(data: &Mutex<ShareMap>) {
+    let mut data = data.lock();
+    let store = data.get_mut::<UnknownEvents>().unwrap();
+
+    if store.dirty {
+        store.save();
+    }
+}
+
+fn save_emoji_usage(data: &Mutex<ShareMap>) {
+    let mut data = data.lock();
+    let usage = data.get_mut::<EmojiUsageStore>().unwrap();
+
+    if usage.dirty {
+        usage.save();
+    }
+}
+
+fn touch_boot_history(data: &Mutex<ShareMap>) {
+    let mut data = data.lock();
+    let history = data.get_mut::<BootHistoryStore>().unwrap();
+    history.touch();
+    history.save();
+}
+
+fn expire_temp_roles(data: &Mutex<ShareMap>) {
+    let now = Utc::now();
+    let due = data.lock().get::<TempRoleStore>().unwrap().due(now);
+
+    for temp_role in due {
+        // It may have been re-granted or extended since it was looked up.
+        if !data.lock().get::<TempRoleStore>().unwrap().is_current(&temp_role) {
+            continue;
+        }
+
+        let res = actions::remove_role(data,
+                                       GuildId(temp_role.guild_id),
+                                       UserId(temp_role.user_id),
+                                       RoleId(temp_role.role_id));
+
+        let description = match res {
+            Ok(()) => format!("Temporary role {} expired for {}",
+                              temp_role.role_id,
+                              temp_role.user_id),
+            Err(why) => {
+                warn!("Err removing temporary role {:?}: {:?}", temp_role, why);
+
+                if now - temp_role.expires_at < Duration::hours(GIVE_UP_AFTER) {
+                    continue;
+                }
+
+                format!("Gave up removing temporary role {} from {}: {:?}",
+                        temp_role.role_id,
+                        temp_role.user_id,
+                        why)
+            },
+        };
+
+        let mut data = data.lock();
+
+        {
+            let store = data.get_mut::<TempRoleStore>().unwrap();
+
+            // Keep it if it was extended while the role was being removed.
+            if !store.remove_expired(&temp_role) {
+                continue;
+            }
+
+            store.save();
+        }
+
+        data.get_mut::<AuditLogStore>().unwrap().record(temp_role.guild_id, description);
+    }
+}
@@ -1,12 +1,89 @@
+use serenity::client::bridge::gateway::{ShardManager, ShardMessenger};
 use serenity::model::id::UserId;
+use serenity::prelude::Mutex;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
 use typemap::Key;
-use ::misc::Uptime;
+use ::actions::DryRun;
+use ::error::Reports;
+use ::misc::{
+    AuditLog,
+    BootHistory,
+    CommandLatency,
+    CommandToggles,
+    CommandUsage,
+    Cooldowns,
+    EmojiUsage,
+    GuildBlocklist,
+    Presence,
+    ShardStatus,
+    StickyRoles,
+    TempRoles,
+    UnknownEventStore,
+    Uptime,
+};
+
+pub struct AuditLogStore;
+
+impl Key for AuditLogStore {
+    type Value = AuditLog;
+}
+
+pub struct BootHistoryStore;
+
+impl Key for BootHistoryStore {
+    type Value = BootHistory;
+}
 
 pub struct CommandCounter;
 
 impl Key for CommandCounter {
-    type Value = HashMap<String, u64>;
+    type Value = CommandUsage;
+}
+
+pub struct CommandTimings;
+
+impl Key for CommandTimings {
+    type Value = CommandLatency;
+}
+
+pub struct CommandToggleStore;
+
+impl Key for CommandToggleStore {
+    type Value = CommandToggles;
+}
+
+pub struct CooldownStore;
+
+impl Key for CooldownStore {
+    type Value = Cooldowns;
+}
+
+/// When the stats command last sampled the process' CPU time, and the CPU
+/// time in seconds at that point.
+pub struct CpuSample;
+
+impl Key for CpuSample {
+    type Value = (Instant, f64);
+}
+
+pub struct DryRunConfig;
+
+impl Key for DryRunConfig {
+    type Value = DryRun;
+}
+
+pub struct EmojiUsageStore;
+
+impl Key for EmojiUsageStore {
+    type Value = EmojiUsage;
+}
+
+pub struct ErrorReports;
+
+impl Key for ErrorReports {
+    type Value = Reports;
 }
 
 pub struct EventCounter;
@@ -15,18 +92,71 @@ impl Key for EventCounter {
     type Value = HashMap<&'static str, u64>;
 }
 
+pub struct GuildBlocklistStore;
+
+impl Key for GuildBlocklistStore {
+    type Value = GuildBlocklist;
+}
+
 pub struct NanoCache;
 
 impl Key for NanoCache {
     type Value = CustomCache;
 }
 
+pub struct PresenceStore;
+
+impl Key for PresenceStore {
+    type Value = Presence;
+}
+
+/// A messenger to each shard, for changing its presence from outside of event
+/// handlers.
+///
+/// Only the messenger is kept, rather than the whole `Context`, since a
+/// context holds the data map that this is stored in.
+pub struct ShardMessengers;
+
+impl Key for ShardMessengers {
+    type Value = HashMap<u64, ShardMessenger>;
+}
+
+pub struct ShardHealth;
+
+impl Key for ShardHealth {
+    type Value = HashMap<u64, ShardStatus>;
+}
+
+pub struct ShardManagerContainer;
+
+impl Key for ShardManagerContainer {
+    type Value = Arc<Mutex<ShardManager>>;
+}
+
 pub struct ShardUptime;
 
 impl Key for ShardUptime {
     type Value = HashMap<u64, Uptime>;
 }
 
+pub struct StickyRoleStore;
+
+impl Key for StickyRoleStore {
+    type Value = StickyRoles;
+}
+
+pub struct TempRoleStore;
+
+impl Key for TempRoleStore {
+    type Value = TempRoles;
+}
+
+pub struct UnknownEvents;
+
+impl Key for UnknownEvents {
+    type Value = UnknownEventStore;
+}
+
 pub struct CustomCache {
     pub owner_id: UserId,
 }
@@ -3,7 +3,7 @@ macro_rules! req {
     ($expr:expr) => {
         match $expr {
             Ok(v) => v,
-            Err(_) => return Ok(()),
+            Err(why) => return Err(::error::Error::from(why).into()),
         }
     }
 }